@@ -0,0 +1,272 @@
+use crate::Error;
+use crate::ImageMeta;
+use crate::bing::image_file_name;
+use crate::json_file::read_json;
+use crate::json_file::write_json_atomic;
+use serde::Deserialize;
+use serde::Serialize;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::Duration;
+use time::Date;
+use time::OffsetDateTime;
+use time::format_description::BorrowedFormatItem;
+use time::macros::format_description;
+use tracing::info;
+use tracing::warn;
+
+const APP_DIR: &str = "BingWallpaper";
+const INDEX_FILE: &str = "index.json";
+const QUARANTINE_DIR: &str = "quarantine";
+const STALE_PART_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+const DATE_FORMAT: &[BorrowedFormatItem<'_>] = format_description!("[year]-[month]-[day]");
+const QUARANTINE_FORMAT: &[BorrowedFormatItem<'_>] =
+    format_description!("[year][month][day][hour][minute][second][subsecond digits:3]");
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Retention {
+    pub keep_days: Option<u32>,
+    pub max_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    pub file: String,
+    pub date: String,
+    pub market: String,
+    pub size: u64,
+    #[serde(with = "time::serde::rfc3339")]
+    pub downloaded_at: OffsetDateTime,
+    #[serde(default)]
+    pub image: ImageMeta,
+}
+
+#[derive(Clone)]
+pub struct Archive {
+    dir: PathBuf,
+    retention: Retention,
+    index_lock: Arc<Mutex<()>>,
+}
+
+impl Archive {
+    pub fn default_dir() -> PathBuf {
+        dirs::picture_dir()
+            .or_else(dirs::data_local_dir)
+            .unwrap_or_else(std::env::temp_dir)
+            .join(APP_DIR)
+    }
+
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            retention: Retention::default(),
+            index_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn with_retention(mut self, retention: Retention) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn retention(&self) -> Retention {
+        self.retention
+    }
+
+    /// 例如 `2026-10-18_en-US_OHR.SomeName_1920x1080.jpg`、`2026-10-18_apod_M31.jpg`
+    pub fn file_name(image: &ImageMeta, image_url: &str) -> String {
+        format!(
+            "{}_{}_{}",
+            image_date(image),
+            image.label(),
+            image_file_name(image_url)
+        )
+    }
+
+    pub fn path_for(&self, image: &ImageMeta, image_url: &str) -> PathBuf {
+        self.dir.join(Self::file_name(image, image_url))
+    }
+
+    pub fn quarantine_dir(&self) -> PathBuf {
+        self.dir.join(QUARANTINE_DIR)
+    }
+
+    /// 将未通过校验的文件复制到隔离目录，便于排查，不会加入索引
+    pub fn quarantine(&self, from: &Path, file_name: &str) -> Result<PathBuf, Error> {
+        let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+        let dir = self.quarantine_dir();
+        std::fs::create_dir_all(&dir)?;
+        let to = dir.join(format!(
+            "{}_{}",
+            now.format(QUARANTINE_FORMAT).unwrap_or_default(),
+            file_name
+        ));
+        std::fs::copy(from, &to)?;
+        Ok(to)
+    }
+
+    pub fn entries(&self) -> Result<Vec<ArchiveEntry>, Error> {
+        let _lock = self
+            .index_lock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        self.read_index()
+    }
+
+    /// 查找来源与 id 相同且分辨率相同（文件名后缀一致）的壁纸
+    pub fn find(&self, image: &ImageMeta, image_url: &str) -> Result<Option<ArchiveEntry>, Error> {
+        if image.id.is_empty() {
+            return Ok(None);
+        }
+
+        let suffix = format!("_{}", image_file_name(image_url));
+        Ok(self.entries()?.into_iter().find(|entry| {
+            entry.image.source == image.source
+                && entry.image.id == image.id
+                && entry.file.ends_with(&suffix)
+        }))
+    }
+
+    /// 按 `keep_days` 加入壁纸库后会立即被清理
+    pub fn is_expired(&self, image: &ImageMeta) -> bool {
+        self.cutoff()
+            .is_some_and(|cutoff| image_date(image) < cutoff)
+    }
+
+    pub fn add(&self, image: &ImageMeta, path: &Path) -> Result<ArchiveEntry, Error> {
+        let entry = ArchiveEntry {
+            file: file_name_of(path)?,
+            date: image_date(image),
+            market: image.label().to_string(),
+            size: std::fs::metadata(path)?.len(),
+            downloaded_at: OffsetDateTime::now_local()
+                .unwrap_or_else(|_| OffsetDateTime::now_utc()),
+            image: image.clone(),
+        };
+
+        let _lock = self
+            .index_lock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut entries = self.read_index()?;
+        entries.retain(|e| e.file != entry.file);
+        entries.push(entry.clone());
+        entries.sort_by(|a, b| a.date.cmp(&b.date).then(a.file.cmp(&b.file)));
+        self.prune_entries(&mut entries);
+        self.write_index(&entries)?;
+
+        Ok(entry)
+    }
+
+    pub fn prune(&self) -> Result<Vec<ArchiveEntry>, Error> {
+        let _lock = self
+            .index_lock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut entries = self.read_index()?;
+        let removed = self.prune_entries(&mut entries);
+        self.write_index(&entries)?;
+        self.remove_stale_parts();
+
+        Ok(removed)
+    }
+
+    /// 进程退出等原因遗留的未完成下载
+    fn remove_stale_parts(&self) {
+        let Ok(dir) = std::fs::read_dir(&self.dir) else {
+            return;
+        };
+        for entry in dir.flatten() {
+            let path = entry.path();
+            let stale = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|modified| modified.elapsed().unwrap_or_default() > STALE_PART_AGE);
+            if !stale || path.extension().is_none_or(|extension| extension != "part") {
+                continue;
+            }
+            match std::fs::remove_file(&path) {
+                Ok(()) => info!("清理未完成的下载: {}", path.display()),
+                Err(e) => warn!("清理未完成的下载失败 {}: {:?}", path.display(), e),
+            }
+        }
+    }
+
+    fn prune_entries(&self, entries: &mut Vec<ArchiveEntry>) -> Vec<ArchiveEntry> {
+        let mut removed = Vec::new();
+
+        if let Some(cutoff) = self.cutoff() {
+            while entries.len() > 1 && entries[0].date < cutoff {
+                removed.push(entries.remove(0));
+            }
+        }
+
+        if let Some(max_bytes) = self.retention.max_bytes {
+            while entries.len() > 1 && entries.iter().map(|e| e.size).sum::<u64>() > max_bytes {
+                removed.push(entries.remove(0));
+            }
+        }
+
+        for entry in &removed {
+            let path = self.dir.join(&entry.file);
+            match std::fs::remove_file(&path) {
+                Ok(()) => info!("清理壁纸: {}", path.display()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => warn!("清理壁纸失败 {}: {:?}", path.display(), e),
+            }
+        }
+
+        removed
+    }
+
+    /// 早于该日期的壁纸按 `keep_days` 清理
+    fn cutoff(&self) -> Option<String> {
+        let keep_days = self.retention.keep_days?;
+        let today = OffsetDateTime::now_local()
+            .unwrap_or_else(|_| OffsetDateTime::now_utc())
+            .date();
+        Some(
+            today
+                .checked_sub(time::Duration::days(keep_days.into()))
+                .unwrap_or(Date::MIN)
+                .format(DATE_FORMAT)
+                .unwrap_or_default(),
+        )
+    }
+
+    fn read_index(&self) -> Result<Vec<ArchiveEntry>, Error> {
+        Ok(read_json(&self.dir.join(INDEX_FILE))?.unwrap_or_default())
+    }
+
+    fn write_index(&self, entries: &[ArchiveEntry]) -> Result<(), Error> {
+        write_json_atomic(&self.dir.join(INDEX_FILE), entries)
+    }
+}
+
+fn image_date(image: &ImageMeta) -> String {
+    image
+        .date()
+        .unwrap_or_else(|| {
+            OffsetDateTime::now_local()
+                .unwrap_or_else(|_| OffsetDateTime::now_utc())
+                .date()
+        })
+        .format(DATE_FORMAT)
+        .unwrap_or_default()
+}
+
+fn file_name_of(path: &Path) -> Result<String, Error> {
+    Ok(path
+        .file_name()
+        .ok_or_else(|| Error::io(path)(io::ErrorKind::InvalidInput.into()))?
+        .to_string_lossy()
+        .into_owned())
+}
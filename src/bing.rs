@@ -0,0 +1,344 @@
+use crate::Error;
+use reqwest::Url;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+use time::Date;
+use time::Duration;
+use time::OffsetDateTime;
+use time::PrimitiveDateTime;
+use time::UtcOffset;
+use time::format_description::BorrowedFormatItem;
+use time::macros::format_description;
+
+pub const DEFAULT_HOST: &str = "https://cn.bing.com";
+pub const DEFAULT_IMAGE_HOST: &str = "https://s.cn.bing.net";
+
+const DEFAULT_FILE_NAME: &str = "wallpaper.jpg";
+
+const DATE_FORMAT: &[BorrowedFormatItem<'_>] = format_description!("[year][month][day]");
+const DATETIME_FORMAT: &[BorrowedFormatItem<'_>] =
+    format_description!("[year][month][day][hour][minute]");
+
+pub const MARKETS: &[&str] = &[
+    "ar-XA", "bg-BG", "cs-CZ", "da-DK", "de-AT", "de-CH", "de-DE", "el-GR", "en-AU", "en-CA",
+    "en-GB", "en-ID", "en-IE", "en-IN", "en-MY", "en-NZ", "en-PH", "en-SG", "en-US", "en-WW",
+    "en-XA", "en-ZA", "es-AR", "es-CL", "es-ES", "es-MX", "es-US", "es-XL", "et-EE", "fi-FI",
+    "fr-BE", "fr-CA", "fr-CH", "fr-FR", "he-IL", "hr-HR", "hu-HU", "it-IT", "ja-JP", "ko-KR",
+    "lt-LT", "lv-LV", "nb-NO", "nl-BE", "nl-NL", "pl-PL", "pt-BR", "pt-PT", "ro-RO", "ru-RU",
+    "sk-SK", "sl-SL", "sv-SE", "th-TH", "tr-TR", "uk-UA", "zh-CN", "zh-HK", "zh-TW",
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HpImage {
+    #[serde(default)]
+    pub startdate: String,
+    #[serde(default)]
+    pub fullstartdate: String,
+    #[serde(default)]
+    pub enddate: String,
+    pub url: String,
+    #[serde(default)]
+    pub urlbase: String,
+    #[serde(default)]
+    pub copyright: String,
+    #[serde(default)]
+    pub copyrightlink: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub quiz: String,
+    #[serde(default)]
+    pub wp: bool,
+    #[serde(default)]
+    pub hsh: String,
+    #[serde(default)]
+    pub drk: i64,
+    #[serde(default)]
+    pub top: i64,
+    #[serde(default)]
+    pub bot: i64,
+    #[serde(default)]
+    pub hs: Vec<serde_json::Value>,
+}
+
+impl HpImage {
+    pub fn start_date(&self) -> Option<Date> {
+        Date::parse(&self.startdate, DATE_FORMAT).ok()
+    }
+
+    pub fn end_date(&self) -> Option<Date> {
+        Date::parse(&self.enddate, DATE_FORMAT).ok()
+    }
+
+    /// `fullstartdate` 为 UTC 时间，例如 `202610171600`
+    pub fn full_start(&self) -> Option<OffsetDateTime> {
+        PrimitiveDateTime::parse(&self.fullstartdate, DATETIME_FORMAT)
+            .ok()
+            .map(PrimitiveDateTime::assume_utc)
+    }
+
+    /// 下一张壁纸的预计发布时间，即 `fullstartdate` 之后一天（夏令时切换当天相差一小时），
+    /// 缺失时取 `enddate` 当天零点（按市场所在时区的标准时间）
+    pub fn next_rollover(&self, market: Market) -> Option<OffsetDateTime> {
+        self.full_start()
+            .map(|start| start + Duration::DAY)
+            .or_else(|| {
+                self.end_date()
+                    .map(|date| date.midnight().assume_offset(market.utc_offset()))
+            })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HpJson {
+    pub images: Vec<HpImage>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Market(&'static str);
+
+impl Market {
+    pub fn new(code: &str) -> Result<Self, Error> {
+        MARKETS
+            .iter()
+            .find(|market| market.eq_ignore_ascii_case(code))
+            .map(|market| Self(market))
+            .ok_or_else(|| Error::Config(format!("未知的市场代码: {}", code)))
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.0
+    }
+
+    /// 市场所在地区的标准时间，不考虑夏令时，夏令时期间比当地零点晚一小时。
+    /// 只在壁纸信息缺少 `fullstartdate` 时使用，`fullstartdate` 已按当地的夏令时给出
+    pub fn utc_offset(&self) -> UtcOffset {
+        let minutes = match self.0 {
+            "en-US" | "es-US" => -8 * 60,
+            "es-MX" => -6 * 60,
+            "en-CA" | "fr-CA" | "es-XL" => -5 * 60,
+            "es-CL" => -4 * 60,
+            "es-AR" | "pt-BR" => -3 * 60,
+            "en-GB" | "en-IE" | "en-WW" | "pt-PT" => 0,
+            "cs-CZ" | "da-DK" | "de-AT" | "de-CH" | "de-DE" | "es-ES" | "fr-BE" | "fr-CH"
+            | "fr-FR" | "hr-HR" | "hu-HU" | "it-IT" | "nb-NO" | "nl-BE" | "nl-NL" | "pl-PL"
+            | "sk-SK" | "sl-SL" | "sv-SE" => 60,
+            "bg-BG" | "el-GR" | "en-ZA" | "et-EE" | "fi-FI" | "he-IL" | "lt-LT" | "lv-LV"
+            | "ro-RO" | "uk-UA" => 2 * 60,
+            "ar-XA" | "en-XA" | "ru-RU" | "tr-TR" => 3 * 60,
+            "en-IN" => 5 * 60 + 30,
+            "en-ID" | "th-TH" => 7 * 60,
+            "en-MY" | "en-PH" | "en-SG" | "zh-CN" | "zh-HK" | "zh-TW" => 8 * 60,
+            "ja-JP" | "ko-KR" => 9 * 60,
+            "en-AU" => 10 * 60,
+            "en-NZ" => 12 * 60,
+            _ => 0,
+        };
+        UtcOffset::from_whole_seconds(minutes * 60).unwrap_or(UtcOffset::UTC)
+    }
+}
+
+impl Default for Market {
+    fn default() -> Self {
+        Self("zh-CN")
+    }
+}
+
+impl FromStr for Market {
+    type Err = Error;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        Self::new(code)
+    }
+}
+
+impl fmt::Display for Market {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HpRequest {
+    host: Url,
+    image_host: Url,
+    market: Market,
+    idx: u8,
+    n: u8,
+}
+
+impl HpRequest {
+    pub const MAX_IDX: u8 = 7;
+    pub const MAX_N: u8 = 8;
+
+    pub fn builder() -> HpRequestBuilder {
+        HpRequestBuilder::default()
+    }
+
+    pub fn market(&self) -> Market {
+        self.market
+    }
+
+    pub fn idx(&self) -> u8 {
+        self.idx
+    }
+
+    pub fn n(&self) -> u8 {
+        self.n
+    }
+
+    /// 使用相同的地址，请求其他市场或其他序号的图片
+    pub fn page(&self, market: Market, idx: u8, n: u8) -> Result<HpRequest, Error> {
+        if idx > Self::MAX_IDX {
+            return Err(Error::Config(format!(
+                "idx 超出范围 (0-{}): {}",
+                Self::MAX_IDX,
+                idx
+            )));
+        }
+        if n == 0 || n > Self::MAX_N {
+            return Err(Error::Config(format!(
+                "n 超出范围 (1-{}): {}",
+                Self::MAX_N,
+                n
+            )));
+        }
+
+        Ok(HpRequest {
+            market,
+            idx,
+            n,
+            ..self.clone()
+        })
+    }
+
+    pub fn url(&self) -> Url {
+        let mut url = self.host.clone();
+        url.set_path("/HPImageArchive.aspx");
+        url.query_pairs_mut()
+            .clear()
+            .append_pair("format", "js")
+            .append_pair("idx", &self.idx.to_string())
+            .append_pair("n", &self.n.to_string())
+            .append_pair("mkt", self.market.code());
+        url
+    }
+
+    pub fn image_url(&self, image_url: &str) -> Result<Url, Error> {
+        self.image_host
+            .join(image_url)
+            .map_err(Error::parse(format!("图片地址 {}", image_url)))
+    }
+}
+
+impl Default for HpRequest {
+    fn default() -> Self {
+        Self::builder()
+            .build()
+            .expect("default HPImageArchive request is valid")
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HpRequestBuilder {
+    host: Option<String>,
+    image_host: Option<String>,
+    market: Market,
+    idx: u8,
+    n: Option<u8>,
+}
+
+impl HpRequestBuilder {
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    /// 图片地址的前缀，未设置时使用 `host`（默认 host 下使用 `DEFAULT_IMAGE_HOST`）
+    pub fn image_host(mut self, image_host: impl Into<String>) -> Self {
+        self.image_host = Some(image_host.into());
+        self
+    }
+
+    pub fn market(mut self, market: Market) -> Self {
+        self.market = market;
+        self
+    }
+
+    pub fn idx(mut self, idx: u8) -> Self {
+        self.idx = idx;
+        self
+    }
+
+    pub fn n(mut self, n: u8) -> Self {
+        self.n = Some(n);
+        self
+    }
+
+    pub fn build(self) -> Result<HpRequest, Error> {
+        let host = self.host.as_deref().unwrap_or(DEFAULT_HOST);
+        let image_host = match (&self.image_host, &self.host) {
+            (Some(image_host), _) => image_host.as_str(),
+            (None, Some(host)) => host.as_str(),
+            (None, None) => DEFAULT_IMAGE_HOST,
+        };
+        let request = HpRequest {
+            host: parse_host(host)?,
+            image_host: parse_host(image_host)?,
+            market: self.market,
+            idx: 0,
+            n: 1,
+        };
+
+        request.page(self.market, self.idx, self.n.unwrap_or(1))
+    }
+}
+
+pub(crate) fn parse_host(host: &str) -> Result<Url, Error> {
+    let url = Url::parse(host).map_err(|e| Error::Config(format!("无效的地址 {}: {}", host, e)))?;
+    if !matches!(url.scheme(), "http" | "https") || url.host().is_none() {
+        return Err(Error::Config(format!("无效的地址: {}", host)));
+    }
+
+    Ok(url)
+}
+
+/// 优先使用必应地址中的 `id` 参数，其他来源使用地址的最后一段
+pub fn image_file_name(image_url: &str) -> String {
+    let url = Url::parse("https://www.bing.com").and_then(|base| base.join(image_url));
+    let Ok(url) = url else {
+        return DEFAULT_FILE_NAME.to_string();
+    };
+    if let Some(name) = url
+        .query_pairs()
+        .find(|(key, _)| key == "id")
+        .map(|(_, value)| value.into_owned())
+        .filter(|name| !name.is_empty() && !name.contains(['/', '\\']))
+    {
+        return name;
+    }
+
+    let name = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .map(|segment| {
+            segment
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect::<String>()
+        })
+        .filter(|name| !name.trim_matches(['.', '_']).is_empty());
+    match name {
+        Some(name) if name.contains('.') => name,
+        Some(name) => format!("{}.jpg", name),
+        None => DEFAULT_FILE_NAME.to_string(),
+    }
+}
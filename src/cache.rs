@@ -0,0 +1,178 @@
+use crate::Error;
+use crate::StateStore;
+use crate::json_file::read_json;
+use crate::json_file::write_json_atomic;
+use reqwest::RequestBuilder;
+use reqwest::Response;
+use reqwest::header::ETAG;
+use reqwest::header::IF_MODIFIED_SINCE;
+use reqwest::header::IF_NONE_MATCH;
+use reqwest::header::IF_RANGE;
+use reqwest::header::LAST_MODIFIED;
+use serde::Deserialize;
+use serde::Serialize;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::error;
+use tracing::info;
+
+const CACHE_FILE: &str = "http_cache.json";
+const MAX_IMAGES: usize = 64;
+
+/// 响应的 `ETag` 与 `Last-Modified`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    pub fn from_response(response: &Response) -> Self {
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        Self {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+
+    /// 添加 `If-None-Match` 与 `If-Modified-Since`，内容未变化时服务器返回 304
+    pub fn conditional(&self, mut request: RequestBuilder) -> RequestBuilder {
+        if let Some(etag) = &self.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        request
+    }
+
+    /// 断点续传时添加 `If-Range`，内容已变化时服务器返回完整内容。弱 ETag 不能用于 `If-Range`
+    pub fn if_range(&self, request: RequestBuilder) -> RequestBuilder {
+        match (&self.etag, &self.last_modified) {
+            (Some(etag), _) if !etag.starts_with("W/") => request.header(IF_RANGE, etag),
+            (_, Some(last_modified)) => request.header(IF_RANGE, last_modified),
+            _ => request,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CacheEntry {
+    url: String,
+    #[serde(flatten)]
+    validators: Validators,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct CacheData {
+    /// 当前壁纸所对应的元数据请求
+    metadata: Option<CacheEntry>,
+    /// 按时间先后排列
+    images: Vec<CacheEntry>,
+}
+
+/// 持久化的 HTTP 缓存校验信息
+#[derive(Clone)]
+pub struct HttpCache {
+    path: Option<PathBuf>,
+    data: Arc<Mutex<CacheData>>,
+}
+
+impl HttpCache {
+    pub fn default_path() -> Option<PathBuf> {
+        StateStore::dir().map(|dir| dir.join(CACHE_FILE))
+    }
+
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            data: Arc::new(Mutex::new(CacheData::default())),
+        }
+    }
+
+    /// 缓存文件不存在或无法解析时从空缓存开始
+    pub fn open(path: PathBuf) -> Self {
+        let data = match read_json(&path) {
+            Ok(Some(data)) => {
+                info!("加载 HTTP 缓存: {}", path.display());
+                data
+            }
+            Ok(None) => CacheData::default(),
+            Err(e) => {
+                error!("加载 HTTP 缓存失败: {:?}", e);
+                CacheData::default()
+            }
+        };
+
+        Self {
+            path: Some(path),
+            data: Arc::new(Mutex::new(data)),
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// 仅当 `url` 与当前壁纸的元数据请求一致时返回
+    pub async fn metadata(&self, url: &str) -> Option<Validators> {
+        let data = self.data.lock().await;
+        data.metadata
+            .as_ref()
+            .filter(|entry| entry.url == url)
+            .map(|entry| entry.validators.clone())
+    }
+
+    pub async fn set_metadata(&self, url: &str, validators: Validators) -> Result<(), Error> {
+        let mut data = self.data.lock().await;
+        data.metadata = (!validators.is_empty()).then(|| CacheEntry {
+            url: url.to_string(),
+            validators,
+        });
+        self.save(&data)
+    }
+
+    pub async fn image(&self, url: &str) -> Option<Validators> {
+        let data = self.data.lock().await;
+        data.images
+            .iter()
+            .find(|entry| entry.url == url)
+            .map(|entry| entry.validators.clone())
+    }
+
+    pub async fn set_image(&self, url: &str, validators: Validators) -> Result<(), Error> {
+        let mut data = self.data.lock().await;
+        data.images.retain(|entry| entry.url != url);
+        if !validators.is_empty() {
+            data.images.push(CacheEntry {
+                url: url.to_string(),
+                validators,
+            });
+        }
+        let overflow = data.images.len().saturating_sub(MAX_IMAGES);
+        data.images.drain(..overflow);
+        self.save(&data)
+    }
+
+    fn save(&self, data: &CacheData) -> Result<(), Error> {
+        if let Some(path) = &self.path {
+            write_json_atomic(path, data)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,270 @@
+use anyhow::Result;
+use bingwallpaper::Config;
+use bingwallpaper::Progress;
+use bingwallpaper::ProgressFn;
+use bingwallpaper::WallpaperError;
+use bingwallpaper::WallpaperService;
+use bingwallpaper::watch_config;
+use clap::Parser;
+use clap::Subcommand;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use tokio::runtime::Runtime;
+use tokio::sync::watch;
+use tracing::info;
+use tracing::warn;
+
+#[derive(Parser)]
+#[command(name = "bingwallpaper", version, about = "必应每日壁纸")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// 配置文件路径，默认为系统配置目录下的 bingwallpaper/config.toml
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+    #[command(flatten)]
+    pub overrides: Overrides,
+}
+
+#[derive(clap::Args, Clone)]
+pub struct Overrides {
+    /// 壁纸来源: bing、apod、wikimedia、unsplash、local
+    #[arg(long, global = true)]
+    pub source: Option<String>,
+    /// 市场代码，例如 zh-CN、en-US、ja-JP
+    #[arg(long, global = true)]
+    pub market: Option<String>,
+    /// HPImageArchive 接口地址，例如 https://www.bing.com
+    #[arg(long, global = true)]
+    pub host: Option<String>,
+    /// 图片序号，0 为今天，最大为 7
+    #[arg(long, global = true)]
+    pub idx: Option<u8>,
+    /// 图片分辨率，例如 auto、UHD、1920x1200、768x1366
+    #[arg(long, global = true)]
+    pub resolution: Option<String>,
+}
+
+impl Overrides {
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(source) = &self.source {
+            config.source.kind = source.clone();
+        }
+        if let Some(market) = &self.market {
+            config.bing.market = market.clone();
+        }
+        if let Some(host) = &self.host {
+            config.bing.host = Some(host.clone());
+        }
+        if let Some(idx) = self.idx {
+            config.bing.idx = idx;
+        }
+        if let Some(resolution) = &self.resolution {
+            config.image.resolution = resolution.clone();
+        }
+    }
+}
+
+pub struct ConfigSource {
+    pub path: Option<PathBuf>,
+    pub config: Config,
+    overrides: Overrides,
+}
+
+type Watching = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+impl ConfigSource {
+    pub fn load(cli: &Cli) -> Result<Self> {
+        let path = cli.config.clone().or_else(Config::default_path);
+        let mut config = match &path {
+            Some(path) => Config::load(path)?,
+            None => Config::default(),
+        };
+        cli.overrides.apply(&mut config);
+        config.validate()?;
+
+        Ok(Self {
+            path,
+            config,
+            overrides: cli.overrides.clone(),
+        })
+    }
+
+    pub fn watch(self) -> (watch::Receiver<Arc<Config>>, Watching) {
+        match self.path {
+            Some(path) => {
+                let overrides = self.overrides;
+                let (receiver, watching) =
+                    watch_config(path, self.config, move |config| overrides.apply(config));
+                (receiver, Box::pin(watching))
+            }
+            None => {
+                let (sender, receiver) = watch::channel(Arc::new(self.config));
+                (receiver, Box::pin(async move { sender.closed().await }))
+            }
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// 下载最新壁纸到指定目录
+    Fetch {
+        #[arg(long, default_value = ".")]
+        out: PathBuf,
+    },
+    /// 将指定图片设置为壁纸
+    Apply { file: PathBuf },
+    /// 获取并应用最新壁纸
+    Update,
+    /// 不显示托盘图标，在前台定时更新壁纸
+    Daemon,
+    /// 显示当前状态
+    Status,
+    /// 补全壁纸库中缺失的最近壁纸（必应约 15 天）
+    Backfill {
+        /// 要补全的必应市场，默认为配置中的 archive.markets，未配置时为当前的壁纸来源
+        #[arg(long, value_delimiter = ',')]
+        markets: Vec<String>,
+    },
+    /// 管理本地壁纸库
+    Archive {
+        #[command(subcommand)]
+        command: ArchiveCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ArchiveCommand {
+    /// 列出已保存的壁纸
+    List,
+    /// 按保留策略清理壁纸
+    Prune,
+}
+
+pub fn run(service: WallpaperService, source: ConfigSource, command: Command) -> Result<()> {
+    let rt = Runtime::new()?;
+    let service = service.with_progress(log_progress());
+
+    match command {
+        Command::Fetch { out } => {
+            let path = rt.block_on(service.fetch_wallpaper(&out))?;
+            println!("{}", path.display());
+        }
+        Command::Apply { file } => {
+            rt.block_on(service.apply_wallpaper(&file))?;
+            println!("{}", file.display());
+        }
+        Command::Update => {
+            rt.block_on(service.update_wallpaper())?;
+        }
+        Command::Daemon => {
+            let (config, watching) = source.watch();
+            rt.spawn(watching);
+            rt.block_on(async {
+                tokio::select! {
+                    _ = service.handle_enable_daily_updating(config) => {}
+                    _ = tokio::signal::ctrl_c() => info!("收到退出信号"),
+                }
+            });
+        }
+        Command::Status => {
+            println!("source: {}", service.source().name());
+            println!("setter: {}", service.setter().name());
+            println!("archive: {}", service.archive().dir().display());
+            if let Some(path) = service.state().path() {
+                println!("state: {}", path.display());
+            }
+            if let Some(path) = service.cache().path() {
+                println!("http cache: {}", path.display());
+            }
+            let state = rt.block_on(service.state().get());
+            if let Some(image) = &state.image {
+                println!("last updated: {}", image.url());
+                println!("title: {}", image.title);
+                println!("copyright: {}", image.copyright);
+            }
+            if let Some(path) = &state.path {
+                println!("applied: {}", path.display());
+            }
+            if let Some(updated_at) = state.updated_at {
+                println!("updated at: {}", updated_at);
+            }
+            match service.archive().entries() {
+                Ok(entries) => println!("archived: {}", entries.len()),
+                Err(e) => warn!("读取壁纸库失败: {:?}", e),
+            }
+            // 只用于对比，离线时仍然显示本地状态
+            match rt.block_on(service.get_latest_image()) {
+                Ok(latest_image) => println!("latest: {}", latest_image.url()),
+                Err(e) => warn!("获取最新壁纸失败: {}", e),
+            }
+        }
+        Command::Backfill { markets } => {
+            let sources = if markets.is_empty() {
+                service.backfill_sources()
+            } else {
+                source.config.bing_sources(&markets)?
+            };
+            let report = rt.block_on(service.backfill(&sources))?;
+            for path in &report.downloaded {
+                println!("{}", path.display());
+            }
+        }
+        Command::Archive { command } => match command {
+            ArchiveCommand::List => {
+                let archive = service.archive();
+                for entry in archive.entries()? {
+                    println!(
+                        "{}\t{}\t{}",
+                        entry.date,
+                        entry.market,
+                        archive.dir().join(&entry.file).display()
+                    );
+                }
+            }
+            ArchiveCommand::Prune => {
+                let archive = service.archive();
+                for entry in archive.prune()? {
+                    println!("{}", archive.dir().join(&entry.file).display());
+                }
+            }
+        },
+    }
+
+    Ok(())
+}
+
+/// 按错误类型区分退出码，见 [`WallpaperError::exit_code`]，其他错误为 1
+pub fn exit_code(error: &anyhow::Error) -> ExitCode {
+    let code = error
+        .chain()
+        .find_map(|e| e.downcast_ref::<WallpaperError>())
+        .map_or(1, WallpaperError::exit_code);
+
+    ExitCode::from(code)
+}
+
+/// 每下载 10% 记录一次进度
+fn log_progress() -> ProgressFn {
+    let last_step = Arc::new(AtomicU64::new(u64::MAX));
+    Arc::new(move |progress: Progress| {
+        let Some(total) = progress.total.filter(|&total| total > 0) else {
+            return;
+        };
+        let step = progress.downloaded * 10 / total;
+        if last_step.swap(step, Ordering::Relaxed) != step {
+            info!(
+                "下载进度: {}% ({}/{} 字节)",
+                step * 10,
+                progress.downloaded,
+                total
+            );
+        }
+    })
+}
@@ -0,0 +1,703 @@
+use crate::ApodSource;
+use crate::Archive;
+use crate::BingSource;
+use crate::Error;
+use crate::HpRequest;
+use crate::ImageCheck;
+use crate::ImageSource;
+use crate::LocalSource;
+use crate::Market;
+use crate::Resolution;
+use crate::Retention;
+use crate::RetryPolicy;
+use crate::Schedule;
+use crate::SlideshowOrder;
+use crate::StallTimeout;
+use crate::StateStore;
+use crate::Timeouts;
+use crate::UnsplashSource;
+use crate::WikimediaSource;
+use reqwest::Url;
+use serde::Deserialize;
+use serde::Serialize;
+use std::future::Future;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+use tokio::sync::watch;
+use tokio::time;
+use tracing::error;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+
+const APP_DIR: &str = "bingwallpaper";
+const CONFIG_FILE: &str = "config.toml";
+const AUTO: &str = "auto";
+const LOG_DIR: &str = "logs";
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub source: SourceConfig,
+    pub bing: BingConfig,
+    pub image: ImageConfig,
+    pub update: UpdateConfig,
+    pub network: NetworkConfig,
+    pub retry: RetryConfig,
+    pub archive: ArchiveConfig,
+    pub log: LogConfig,
+    pub menu: MenuConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SourceConfig {
+    /// `bing`、`apod`、`wikimedia`、`unsplash` 或 `local`
+    pub kind: String,
+    pub apod: ApodConfig,
+    pub wikimedia: WikimediaConfig,
+    pub unsplash: UnsplashConfig,
+    pub local: LocalConfig,
+}
+
+impl Default for SourceConfig {
+    fn default() -> Self {
+        Self {
+            kind: BingSource::NAME.to_string(),
+            apod: ApodConfig::default(),
+            wikimedia: WikimediaConfig::default(),
+            unsplash: UnsplashConfig::default(),
+            local: LocalConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApodConfig {
+    pub host: Option<String>,
+    /// 未设置时使用 `DEMO_KEY`，每小时限制 30 次请求
+    pub api_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WikimediaConfig {
+    pub host: Option<String>,
+    /// 维基百科的语言代码，默认为 `en`
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UnsplashConfig {
+    pub host: Option<String>,
+    pub access_key: String,
+    /// 搜索关键词，例如 `nature`
+    pub query: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LocalConfig {
+    pub dir: Option<PathBuf>,
+    /// 包含子目录中的图片
+    pub recursive: bool,
+    /// 例如 `*.jpg`、`favorites/**`，为空时包含所有图片
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    /// `sequential`、`shuffle` 或 `recent`
+    pub order: String,
+    /// 轮换间隔，代替 `[update]` 中的计划
+    pub interval_secs: u64,
+}
+
+impl Default for LocalConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            recursive: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            order: SlideshowOrder::default().to_string(),
+            interval_secs: 30 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BingConfig {
+    pub market: String,
+    pub host: Option<String>,
+    pub image_host: Option<String>,
+    pub idx: u8,
+    /// 每次请求的图片数 (1-8)，应用其中的第一张
+    pub n: u8,
+}
+
+impl Default for BingConfig {
+    fn default() -> Self {
+        Self {
+            market: Market::default().to_string(),
+            host: None,
+            image_host: None,
+            idx: 0,
+            n: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageConfig {
+    /// `auto` 根据屏幕分辨率选择，也可以指定 `UHD`、`1920x1200`、`768x1366` 等
+    pub resolution: String,
+    /// 下载的图片至少需要的尺寸，不区分横竖
+    pub min_width: u32,
+    pub min_height: u32,
+}
+
+impl Default for ImageConfig {
+    fn default() -> Self {
+        let check = ImageCheck::default();
+        Self {
+            resolution: AUTO.to_string(),
+            min_width: check.min_width,
+            min_height: check.min_height,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateConfig {
+    /// 根据必应的发布时间安排更新，关闭时按 `interval_secs` 定时更新
+    pub smart: bool,
+    /// 无法预计发布时间时的更新间隔
+    pub interval_secs: u64,
+    /// 预计发布时间之后等待的秒数
+    pub rollover_delay_secs: u64,
+    /// 发布后新壁纸尚未出现时的轮询间隔
+    pub poll_interval_secs: u64,
+    /// 发布后最多轮询的秒数，超过后按 `interval_secs` 更新
+    pub poll_window_secs: u64,
+    /// 系统事件触发后等待的秒数，期间的重复事件会被合并
+    pub debounce_secs: u64,
+    /// 从休眠中唤醒后更新
+    pub on_resume: bool,
+    /// 网络连接后更新
+    pub on_network: bool,
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        Self {
+            smart: true,
+            interval_secs: 60 * 60,
+            rollover_delay_secs: 60,
+            poll_interval_secs: 5 * 60,
+            poll_window_secs: 60 * 60,
+            debounce_secs: 10,
+            on_resume: true,
+            on_network: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    pub connect_timeout_secs: u64,
+    /// 获取壁纸信息的总超时
+    #[serde(alias = "timeout_secs")]
+    pub metadata_timeout_secs: u64,
+    /// 下载单张图片的总超时，0 表示不限制
+    pub download_timeout_secs: u64,
+    /// 下载时每 `stall_timeout_secs` 秒内的平均速度低于 `min_speed_bytes` 字节/秒则视为停滞
+    pub stall_timeout_secs: u64,
+    pub min_speed_bytes: u64,
+    /// 例如 `http://proxy.example.com:8080`、`socks5h://127.0.0.1:1080`，
+    /// 用户名和密码可以写在地址中，也可以单独设置
+    pub proxy: Option<String>,
+    pub proxy_username: Option<String>,
+    pub proxy_password: Option<String>,
+    /// 不经过 `proxy` 的主机，例如 `localhost`、`.example.com`
+    pub no_proxy: Vec<String>,
+    /// 未设置 `proxy` 时使用 `HTTPS_PROXY`、`HTTP_PROXY`、`ALL_PROXY` 与 `NO_PROXY` 环境变量
+    pub env_proxy: bool,
+    /// 额外信任的根证书（PEM 格式，可以包含多个证书）
+    pub ca_certs: Vec<PathBuf>,
+    pub user_agent: Option<String>,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: 5,
+            metadata_timeout_secs: 10,
+            download_timeout_secs: 10 * 60,
+            stall_timeout_secs: 30,
+            min_speed_bytes: 1024,
+            proxy: None,
+            proxy_username: None,
+            proxy_password: None,
+            no_proxy: Vec::new(),
+            env_proxy: true,
+            ca_certs: Vec::new(),
+            user_agent: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryConfig {
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub jitter: bool,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        let policy = RetryPolicy::default();
+        Self {
+            max_attempts: policy.max_attempts,
+            base_delay_ms: policy.base_delay.as_millis() as u64,
+            max_delay_ms: policy.max_delay.as_millis() as u64,
+            jitter: policy.jitter,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArchiveConfig {
+    pub dir: Option<PathBuf>,
+    pub keep_days: Option<u32>,
+    pub max_mb: Option<u64>,
+    /// 定时更新时同时补全壁纸库中缺失的壁纸，每天最多一次
+    pub backfill: bool,
+    /// 要补全的必应市场，为空时为当前的壁纸来源，`backfill` 子命令未指定市场时同样使用
+    pub markets: Vec<String>,
+}
+
+/// 修改后需要重启才能生效
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    /// 日志级别或 `RUST_LOG` 格式的过滤规则，设置了 `RUST_LOG` 环境变量时以环境变量为准
+    pub level: String,
+    /// `text` 或 `json`
+    pub format: String,
+    /// 默认为状态目录下的 logs
+    pub dir: Option<PathBuf>,
+    /// 日志按天轮转，保留的文件数
+    pub max_files: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            format: "text".to_string(),
+            dir: None,
+            max_files: 7,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MenuConfig {
+    pub enable_daily_update: String,
+    pub daily_update_enabled: String,
+    pub update: String,
+    pub learn_more: String,
+    pub open_image: String,
+    pub open_folder: String,
+    pub open_log: String,
+    pub copy_info: String,
+    pub exit: String,
+}
+
+impl Default for MenuConfig {
+    fn default() -> Self {
+        Self {
+            enable_daily_update: "开启每日更新".to_string(),
+            daily_update_enabled: "已开启每日更新".to_string(),
+            update: "更新壁纸".to_string(),
+            learn_more: "了解详情".to_string(),
+            open_image: "打开当前壁纸".to_string(),
+            open_folder: "打开壁纸文件夹".to_string(),
+            open_log: "打开日志".to_string(),
+            copy_info: "复制壁纸信息".to_string(),
+            exit: "退出".to_string(),
+        }
+    }
+}
+
+impl Config {
+    pub fn dir() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(APP_DIR))
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        Self::dir().map(|dir| dir.join(CONFIG_FILE))
+    }
+
+    /// 配置文件不存在时返回默认配置。不做校验，命令行参数可能覆盖其中的字段，
+    /// 调用方应在覆盖之后调用 [`Config::validate`]
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(path).map_err(Error::io(path))?;
+        let mut config: Self = toml::from_str(&content)
+            .map_err(Error::parse(format!("配置文件 {}", path.display())))?;
+        config.expand_home();
+
+        Ok(config)
+    }
+
+    /// 将路径开头的 `~` 替换为用户主目录
+    fn expand_home(&mut self) {
+        let paths = [
+            self.source.local.dir.as_mut(),
+            self.archive.dir.as_mut(),
+            self.log.dir.as_mut(),
+        ];
+        for path in paths
+            .into_iter()
+            .flatten()
+            .chain(self.network.ca_certs.iter_mut())
+        {
+            if let Ok(rest) = path.strip_prefix("~")
+                && let Some(home) = dirs::home_dir()
+            {
+                *path = home.join(rest);
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if !(1..=HpRequest::MAX_N).contains(&self.bing.n) {
+            return Err(Error::Config(format!(
+                "bing.n 必须在 1 到 {} 之间: {}",
+                HpRequest::MAX_N,
+                self.bing.n
+            )));
+        }
+        self.request()?;
+        match self.source.kind.as_str() {
+            BingSource::NAME => {}
+            ApodSource::NAME => {
+                ApodSource::new(
+                    self.source.apod.host.as_deref(),
+                    self.source.apod.api_key.as_deref(),
+                )?;
+            }
+            WikimediaSource::NAME => {
+                WikimediaSource::new(
+                    self.source.wikimedia.host.as_deref(),
+                    self.source.wikimedia.language.as_deref(),
+                    None,
+                )?;
+            }
+            UnsplashSource::NAME => {
+                UnsplashSource::new(
+                    self.source.unsplash.host.as_deref(),
+                    &self.source.unsplash.access_key,
+                    self.source.unsplash.query.as_deref(),
+                    None,
+                )?;
+            }
+            LocalSource::NAME => {
+                self.local_source()?;
+                if self.source.local.interval_secs == 0 {
+                    return Err(Error::Config(
+                        "source.local.interval_secs 必须大于 0".to_string(),
+                    ));
+                }
+            }
+            kind => return Err(Error::Config(format!("未知的壁纸来源: {}", kind))),
+        }
+        if !self.image.resolution.eq_ignore_ascii_case(AUTO) {
+            self.image.resolution.parse::<Resolution>()?;
+        }
+
+        if self.update.interval_secs == 0 {
+            return Err(Error::Config("update.interval_secs 必须大于 0".to_string()));
+        }
+        if self.update.poll_interval_secs == 0 {
+            return Err(Error::Config(
+                "update.poll_interval_secs 必须大于 0".to_string(),
+            ));
+        }
+        if self.network.metadata_timeout_secs == 0 {
+            return Err(Error::Config(
+                "network.metadata_timeout_secs 必须大于 0".to_string(),
+            ));
+        }
+        if self.network.stall_timeout_secs == 0 {
+            return Err(Error::Config(
+                "network.stall_timeout_secs 必须大于 0".to_string(),
+            ));
+        }
+        if self.network.connect_timeout_secs == 0 {
+            return Err(Error::Config(
+                "network.connect_timeout_secs 必须大于 0".to_string(),
+            ));
+        }
+        if let Some(proxy) = &self.network.proxy {
+            Url::parse(proxy).map_err(|e| Error::Config(format!("network.proxy 无效: {}", e)))?;
+        }
+        if self.network.proxy_password.is_some() && self.network.proxy_username.is_none() {
+            return Err(Error::Config(
+                "设置 network.proxy_password 时需要同时设置 network.proxy_username".to_string(),
+            ));
+        }
+        if self.retry.max_attempts == 0 {
+            return Err(Error::Config("retry.max_attempts 必须大于 0".to_string()));
+        }
+        if self.retry.base_delay_ms > self.retry.max_delay_ms {
+            return Err(Error::Config(
+                "retry.base_delay_ms 不能大于 retry.max_delay_ms".to_string(),
+            ));
+        }
+        if self.archive.keep_days == Some(0) {
+            return Err(Error::Config("archive.keep_days 必须大于 0".to_string()));
+        }
+        if self.archive.max_mb == Some(0) {
+            return Err(Error::Config("archive.max_mb 必须大于 0".to_string()));
+        }
+        for market in &self.archive.markets {
+            Market::new(market)?;
+        }
+        EnvFilter::try_new(&self.log.level)
+            .map_err(|e| Error::Config(format!("log.level 无效: {}", e)))?;
+        if !matches!(self.log.format.as_str(), "text" | "json") {
+            return Err(Error::Config(format!(
+                "log.format 只能为 text 或 json: {}",
+                self.log.format
+            )));
+        }
+        if self.log.max_files == 0 {
+            return Err(Error::Config("log.max_files 必须大于 0".to_string()));
+        }
+
+        Ok(())
+    }
+
+    pub fn request(&self) -> Result<HpRequest, Error> {
+        let mut builder = HpRequest::builder()
+            .market(Market::new(&self.bing.market)?)
+            .idx(self.bing.idx)
+            .n(self.bing.n);
+        if let Some(host) = &self.bing.host {
+            builder = builder.host(host);
+        }
+        if let Some(image_host) = &self.bing.image_host {
+            builder = builder.image_host(image_host);
+        }
+
+        builder.build()
+    }
+
+    pub fn source(&self) -> Result<Arc<dyn ImageSource>, Error> {
+        let source = &self.source;
+        Ok(match source.kind.as_str() {
+            BingSource::NAME => Arc::new(BingSource::new(self.request()?, self.resolution()?)),
+            ApodSource::NAME => Arc::new(ApodSource::new(
+                source.apod.host.as_deref(),
+                source.apod.api_key.as_deref(),
+            )?),
+            WikimediaSource::NAME => Arc::new(WikimediaSource::new(
+                source.wikimedia.host.as_deref(),
+                source.wikimedia.language.as_deref(),
+                self.resolution()?,
+            )?),
+            UnsplashSource::NAME => Arc::new(UnsplashSource::new(
+                source.unsplash.host.as_deref(),
+                &source.unsplash.access_key,
+                source.unsplash.query.as_deref(),
+                self.resolution()?,
+            )?),
+            LocalSource::NAME => {
+                let mut local = self.local_source()?;
+                if let Some(path) = LocalSource::default_state_path() {
+                    local = local.with_state_path(path);
+                }
+                Arc::new(local)
+            }
+            kind => return Err(Error::Config(format!("未知的壁纸来源: {}", kind))),
+        })
+    }
+
+    /// 指定市场的必应壁纸，其余设置与 `[bing]` 相同
+    pub fn bing_sources(&self, markets: &[String]) -> Result<Vec<Arc<dyn ImageSource>>, Error> {
+        let bing = BingSource::new(self.request()?, self.resolution()?);
+        markets
+            .iter()
+            .map(|market| {
+                let source: Arc<dyn ImageSource> =
+                    Arc::new(bing.with_market(Market::new(market)?)?);
+                Ok(source)
+            })
+            .collect()
+    }
+
+    fn local_source(&self) -> Result<LocalSource, Error> {
+        let local = &self.source.local;
+        let dir = local
+            .dir
+            .clone()
+            .ok_or_else(|| Error::Config("source.local.dir 不能为空".to_string()))?;
+        Ok(LocalSource::new(dir)
+            .with_recursive(local.recursive)
+            .with_filters(&local.include, &local.exclude)?
+            .with_order(local.order.parse()?)
+            .with_interval(Duration::from_secs(local.interval_secs)))
+    }
+
+    /// 为 `auto` 且无法检测屏幕分辨率时返回 `None`，即使用接口返回的默认地址
+    pub fn resolution(&self) -> Result<Option<Resolution>, Error> {
+        if self.image.resolution.eq_ignore_ascii_case(AUTO) {
+            return Ok(Resolution::detect());
+        }
+
+        Ok(Some(self.image.resolution.parse()?))
+    }
+
+    pub fn image_check(&self) -> ImageCheck {
+        ImageCheck {
+            min_width: self.image.min_width,
+            min_height: self.image.min_height,
+        }
+    }
+
+    pub fn retry(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.retry.max_attempts,
+            base_delay: Duration::from_millis(self.retry.base_delay_ms),
+            max_delay: Duration::from_millis(self.retry.max_delay_ms),
+            jitter: self.retry.jitter,
+        }
+    }
+
+    pub fn archive(&self) -> Archive {
+        let dir = self
+            .archive
+            .dir
+            .clone()
+            .unwrap_or_else(Archive::default_dir);
+        Archive::new(dir).with_retention(Retention {
+            keep_days: self.archive.keep_days,
+            max_bytes: self.archive.max_mb.map(|mb| mb * 1024 * 1024),
+        })
+    }
+
+    pub fn log_dir(&self) -> Option<PathBuf> {
+        self.log
+            .dir
+            .clone()
+            .or_else(|| StateStore::dir().map(|dir| dir.join(LOG_DIR)))
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.update.interval_secs)
+    }
+
+    pub fn schedule(&self) -> Schedule {
+        Schedule {
+            interval: self.interval(),
+            debounce: Duration::from_secs(self.update.debounce_secs),
+            on_resume: self.update.on_resume,
+            on_network: self.update.on_network,
+            smart: self.update.smart,
+            rollover_delay: Duration::from_secs(self.update.rollover_delay_secs),
+            poll_interval: Duration::from_secs(self.update.poll_interval_secs),
+            poll_window: Duration::from_secs(self.update.poll_window_secs),
+        }
+    }
+
+    pub fn timeouts(&self) -> Timeouts {
+        Timeouts {
+            metadata: Duration::from_secs(self.network.metadata_timeout_secs),
+            download: (self.network.download_timeout_secs > 0)
+                .then(|| Duration::from_secs(self.network.download_timeout_secs)),
+            stall: StallTimeout {
+                window: Duration::from_secs(self.network.stall_timeout_secs),
+                min_bytes_per_sec: self.network.min_speed_bytes,
+            },
+        }
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.network.connect_timeout_secs)
+    }
+}
+
+/// 定时检查配置文件，修改后重新加载并通过返回的 `watch::Receiver` 通知，
+/// `overrides` 会在每次加载后、校验前应用（例如命令行参数）
+pub fn watch_config<F>(
+    path: PathBuf,
+    config: Config,
+    overrides: F,
+) -> (
+    watch::Receiver<Arc<Config>>,
+    impl Future<Output = ()> + Send + 'static,
+)
+where
+    F: Fn(&mut Config) + Send + Sync + 'static,
+{
+    let (sender, receiver) = watch::channel(Arc::new(config));
+
+    let watching = async move {
+        let mut last_modified = modified(&path);
+        let mut interval = time::interval(WATCH_INTERVAL);
+        interval.set_missed_tick_behavior(time::MissedTickBehavior::Skip);
+        loop {
+            interval.tick().await;
+            if sender.is_closed() {
+                break;
+            }
+
+            let current_modified = modified(&path);
+            if current_modified == last_modified {
+                continue;
+            }
+            last_modified = current_modified;
+
+            let config = Config::load(&path).and_then(|mut config| {
+                overrides(&mut config);
+                config.validate()?;
+                Ok(config)
+            });
+            match config {
+                Ok(config) => {
+                    info!("重新加载配置: {}", path.display());
+                    sender.send_if_modified(|current| {
+                        if **current == config {
+                            return false;
+                        }
+                        *current = Arc::new(config);
+                        true
+                    });
+                }
+                Err(e) => error!("重新加载配置失败: {:?}", e),
+            }
+        }
+    };
+
+    (receiver, watching)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
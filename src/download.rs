@@ -0,0 +1,587 @@
+use crate::Archive;
+use crate::Config;
+use crate::Error;
+use crate::HttpCache;
+use crate::ImageCheck;
+use crate::ImageMeta;
+use crate::InvalidImage;
+use crate::RetryPolicy;
+use crate::Validators;
+use reqwest::Certificate;
+use reqwest::Client;
+use reqwest::NoProxy;
+use reqwest::Proxy;
+use reqwest::Response;
+use reqwest::StatusCode;
+use reqwest::header::CONTENT_RANGE;
+use reqwest::header::HeaderMap;
+use reqwest::header::RANGE;
+use serde::de::DeserializeOwned;
+use std::ffi::OsString;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+use tokio::time::Instant;
+use tokio::time::timeout;
+use tracing::info;
+use tracing::warn;
+
+const USER_AGENT: &str = concat!("BingWallpaper/", env!("CARGO_PKG_VERSION"));
+
+/// 下载进度，`total` 为服务器告知的总字节数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub downloaded: u64,
+    pub total: Option<u64>,
+}
+
+pub type ProgressFn = Arc<dyn Fn(Progress) + Send + Sync>;
+
+/// 各阶段的超时，连接超时由 `Client` 负责
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    pub metadata: Duration,
+    /// 为 `None` 时只受 `stall` 限制
+    pub download: Option<Duration>,
+    pub stall: StallTimeout,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            metadata: Duration::from_secs(10),
+            download: Some(Duration::from_secs(10 * 60)),
+            stall: StallTimeout::default(),
+        }
+    }
+}
+
+/// 每个 `window` 内的平均速度低于 `min_bytes_per_sec` 时中止下载
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StallTimeout {
+    pub window: Duration,
+    pub min_bytes_per_sec: u64,
+}
+
+impl Default for StallTimeout {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(30),
+            min_bytes_per_sec: 1024,
+        }
+    }
+}
+
+impl StallTimeout {
+    /// 按毫秒计算，`window` 小于 1 秒时不会得到 0
+    fn min_bytes(&self) -> u64 {
+        let min_bytes = u128::from(self.min_bytes_per_sec) * self.window.as_millis() / 1000;
+        u64::try_from(min_bytes).unwrap_or(u64::MAX)
+    }
+}
+
+/// 各图片来源共用的 HTTP 客户端，负责重试、超时、缓存、断点续传与图片校验
+#[derive(Clone)]
+pub struct Downloader {
+    client: Client,
+    retry: RetryPolicy,
+    check: ImageCheck,
+    timeouts: Timeouts,
+    progress: Option<ProgressFn>,
+    cache: HttpCache,
+    archive: Archive,
+}
+
+impl Downloader {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            retry: RetryPolicy::default(),
+            check: ImageCheck::default(),
+            timeouts: Timeouts::default(),
+            progress: None,
+            cache: HttpCache::in_memory(),
+            archive: Archive::new(Archive::default_dir()),
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Result<Self, Error> {
+        let mut downloader = Self::new(build_client(config)?);
+        downloader.reconfigure(config)?;
+
+        Ok(downloader)
+    }
+
+    /// 保留缓存与进度回调
+    pub fn reconfigure(&mut self, config: &Config) -> Result<(), Error> {
+        self.client = build_client(config)?;
+        self.retry = config.retry();
+        self.check = config.image_check();
+        self.timeouts = config.timeouts();
+        self.archive = config.archive();
+
+        Ok(())
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_image_check(mut self, check: ImageCheck) -> Self {
+        self.check = check;
+        self
+    }
+
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    pub fn with_progress(mut self, progress: ProgressFn) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    pub fn with_cache(mut self, cache: HttpCache) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// 未通过校验的图片隔离到该壁纸库下
+    pub fn with_archive(mut self, archive: Archive) -> Self {
+        self.archive = archive;
+        self
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub fn cache(&self) -> &HttpCache {
+        &self.cache
+    }
+
+    pub async fn get_json<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        headers: &HeaderMap,
+    ) -> Result<T, Error> {
+        let (json, _) = self
+            .get_json_if_modified(url, headers, None)
+            .await?
+            .ok_or_else(|| Error::HttpStatus {
+                url: url.to_string(),
+                status: StatusCode::NOT_MODIFIED,
+            })?;
+
+        Ok(json)
+    }
+
+    /// `cached` 不为空时发送条件请求，未变化 (304) 时返回 `None`
+    pub async fn get_json_if_modified<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        headers: &HeaderMap,
+        cached: Option<&Validators>,
+    ) -> Result<Option<(T, Validators)>, Error> {
+        self.retry
+            .retry("获取壁纸信息", || {
+                self.try_get_json(url, headers, cached)
+            })
+            .await
+    }
+
+    async fn try_get_json<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        headers: &HeaderMap,
+        cached: Option<&Validators>,
+    ) -> Result<Option<(T, Validators)>, Error> {
+        let mut request = self
+            .client
+            .get(url)
+            .headers(headers.clone())
+            .timeout(self.timeouts.metadata);
+        if let Some(cached) = cached {
+            request = cached.conditional(request);
+        }
+        let response = request.send().await?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        let response = response.error_for_status()?;
+        let validators = Validators::from_response(&response);
+        let body = response.bytes().await?;
+        let json = serde_json::from_slice::<T>(&body).map_err(Error::parse(url))?;
+
+        Ok(Some((json, validators)))
+    }
+
+    /// 只关心状态码的请求（例如下载统计），使用获取壁纸信息的超时，不重试
+    pub async fn ping(&self, url: &str, headers: &HeaderMap) -> Result<(), Error> {
+        self.client
+            .get(url)
+            .headers(headers.clone())
+            .timeout(self.timeouts.metadata)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    /// 依次尝试 `meta.urls`，保存到 `to_dir` 下，文件名见 [`Archive::file_name`]
+    pub async fn download_first(&self, meta: &ImageMeta, to_dir: &Path) -> Result<PathBuf, Error> {
+        let mut invalid = None;
+        for image_url in &meta.urls {
+            let to_path = to_dir.join(Archive::file_name(meta, image_url));
+            if to_path.exists() {
+                info!("壁纸已存在: {}", to_path.display());
+                return Ok(to_path);
+            }
+
+            info!("下载壁纸: {}", image_url);
+            match self.download_image(image_url, &to_path).await {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(Error::InvalidImage(e)) => {
+                    invalid = Some(e);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+            info!("保存壁纸: {}", to_path.display());
+
+            return Ok(to_path);
+        }
+
+        Err(match invalid {
+            Some(e) => e.into(),
+            None => Error::EmptyFeed(format!("{} 的图片地址均不可用", meta.id)),
+        })
+    }
+
+    /// 图片不存在 (404) 时返回 `false`，重试用尽后删除未完成的下载
+    async fn download_image(&self, image_url: &str, to_path: &Path) -> Result<bool, Error> {
+        let result = self
+            .retry
+            .retry("下载壁纸", || self.try_download(image_url, to_path))
+            .await;
+        if result.is_err() {
+            remove_part(&part_path(to_path));
+        }
+
+        result
+    }
+
+    /// 同一图片可能同时被多处下载（托盘、定时任务、命令行），每次下载写入各自的临时文件，
+    /// 完成并校验后再重命名。已有 `.part` 时取走它从断点继续，中断后放回以便下次继续
+    async fn try_download(&self, image_url: &str, to_path: &Path) -> Result<bool, Error> {
+        let to_dir = to_path
+            .parent()
+            .ok_or_else(|| Error::io(to_path)(std::io::ErrorKind::InvalidInput.into()))?;
+        std::fs::create_dir_all(to_dir).map_err(Error::io(to_dir))?;
+        let part_path = part_path(to_path);
+        let temp_path = temp_path(to_path);
+        let resume_from = claim_part(&part_path, &temp_path).await;
+
+        let result = self
+            .download_to(image_url, to_path, &temp_path, resume_from)
+            .await;
+        match &result {
+            Err(Error::Interrupted { .. } | Error::Network { .. }) => {
+                if let Err(e) = tokio::fs::rename(&temp_path, &part_path).await
+                    && e.kind() != std::io::ErrorKind::NotFound
+                {
+                    warn!("保存未完成的下载失败: {:?}", e);
+                }
+            }
+            _ => remove_part(&temp_path),
+        }
+
+        result
+    }
+
+    async fn download_to(
+        &self,
+        image_url: &str,
+        to_path: &Path,
+        part_path: &Path,
+        resume_from: u64,
+    ) -> Result<bool, Error> {
+        let mut image_request = self.client.get(image_url);
+        if let Some(download_timeout) = self.timeouts.download {
+            image_request = image_request.timeout(download_timeout);
+        }
+        if resume_from > 0 {
+            info!("继续下载: 已下载 {} 字节", resume_from);
+            image_request = image_request.header(RANGE, format!("bytes={}-", resume_from));
+            if let Some(cached) = self.cache.image(image_url).await {
+                image_request = cached.if_range(image_request);
+            }
+        }
+        let image_response = image_request.send().await?;
+        match image_response.status() {
+            StatusCode::NOT_FOUND => {
+                info!("壁纸不存在: {}", image_response.url());
+                remove_part(part_path);
+                return Ok(false);
+            }
+            StatusCode::RANGE_NOT_SATISFIABLE => {
+                remove_part(part_path);
+                return Err(interrupted(image_url, "无法继续下载，将重新下载"));
+            }
+            _ => {}
+        }
+        let image_response = image_response.error_for_status()?;
+        if image_response.status() != StatusCode::PARTIAL_CONTENT
+            && let Err(e) = self
+                .cache
+                .set_image(image_url, Validators::from_response(&image_response))
+                .await
+        {
+            warn!("保存 HTTP 缓存失败: {:?}", e);
+        }
+
+        let offset = if image_response.status() == StatusCode::PARTIAL_CONTENT {
+            if content_range_start(&image_response) != Some(resume_from) {
+                remove_part(part_path);
+                return Err(interrupted(image_url, "续传位置不一致，将重新下载"));
+            }
+            resume_from
+        } else {
+            0
+        };
+        let total = image_response
+            .content_length()
+            .map(|content_length| offset + content_length);
+        let content_check = self.check.check_response(&image_response);
+
+        let received = write_part(
+            image_response,
+            image_url,
+            part_path,
+            offset,
+            total,
+            self.timeouts.stall,
+            self.progress.as_ref(),
+        )
+        .await?;
+
+        if let Some(total) = total
+            && received != total
+        {
+            return Err(interrupted(
+                image_url,
+                format!("下载不完整: 收到 {} 字节，应为 {} 字节", received, total),
+            ));
+        }
+
+        // 读取与完整解码较大的图片较慢，不在异步任务中进行
+        let file_check = match content_check {
+            Ok(()) => {
+                let (check, path) = (self.check, part_path.to_path_buf());
+                tokio::task::spawn_blocking(move || check.check_file(&path).map(|_| ()))
+                    .await
+                    .unwrap_or_else(|e| Err(InvalidImage(format!("校验任务异常结束: {}", e))))
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = file_check {
+            let file_name = to_path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            match self.archive.quarantine(part_path, &file_name) {
+                Ok(quarantined) => warn!("{}，已隔离到: {}", e, quarantined.display()),
+                Err(quarantine_error) => warn!("{}，隔离失败: {:?}", e, quarantine_error),
+            }
+            remove_part(part_path);
+            return Err(e.into());
+        }
+
+        persist(part_path, to_path).await?;
+
+        Ok(true)
+    }
+}
+
+fn build_client(config: &Config) -> Result<Client, Error> {
+    let network = &config.network;
+    let mut builder = Client::builder()
+        .pool_idle_timeout(Duration::ZERO)
+        .pool_max_idle_per_host(0)
+        .connect_timeout(config.connect_timeout())
+        .user_agent(network.user_agent.as_deref().unwrap_or(USER_AGENT));
+
+    if let Some(proxy_url) = &network.proxy {
+        let mut proxy = Proxy::all(proxy_url)
+            .map_err(|e| Error::Config(format!("network.proxy 无效: {}", e)))?;
+        if let Some(username) = &network.proxy_username {
+            proxy = proxy.basic_auth(
+                username,
+                network.proxy_password.as_deref().unwrap_or_default(),
+            );
+        }
+        proxy = proxy.no_proxy(NoProxy::from_string(&network.no_proxy.join(",")));
+        builder = builder.proxy(proxy);
+    } else if !network.env_proxy {
+        builder = builder.no_proxy();
+    }
+
+    for path in &network.ca_certs {
+        let pem = std::fs::read(path).map_err(Error::io(path))?;
+        let certificates = Certificate::from_pem_bundle(&pem)
+            .map_err(Error::parse(format!("证书 {}", path.display())))?;
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    builder
+        .build()
+        .map_err(|e| Error::Config(format!("创建 HTTP 客户端失败: {}", e)))
+}
+
+fn remove_part(part_path: &Path) {
+    if let Err(e) = std::fs::remove_file(part_path)
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        warn!("删除未完成的下载失败: {:?}", e);
+    }
+}
+
+/// 可以重试的下载中断
+fn interrupted(url: &str, reason: impl Into<String>) -> Error {
+    Error::Interrupted {
+        url: url.to_string(),
+        reason: reason.into(),
+    }
+}
+
+/// 例如 `wallpaper.jpg.part`
+fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(".part");
+    path.with_file_name(name)
+}
+
+/// 本次下载独占的临时文件，例如 `wallpaper.jpg.1234-0.part`
+fn temp_path(path: &Path) -> PathBuf {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(format!(
+        ".{}-{}.part",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    path.with_file_name(name)
+}
+
+/// 将 `.part` 重命名为 `temp_path`，重命名是原子的，只有一处下载能取走它。
+/// 返回已下载的字节数，用于断点续传
+async fn claim_part(part_path: &Path, temp_path: &Path) -> u64 {
+    if tokio::fs::rename(part_path, temp_path).await.is_err() {
+        return 0;
+    }
+
+    tokio::fs::metadata(temp_path)
+        .await
+        .map(|metadata| metadata.len())
+        .unwrap_or(0)
+}
+
+/// 解析 `Content-Range: bytes 100-199/200` 中的起始位置
+fn content_range_start(response: &Response) -> Option<u64> {
+    let range = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let (start, _) = range.strip_prefix("bytes ")?.split_once('-')?;
+    start.trim().parse().ok()
+}
+
+/// 将响应写入 `part_path`，`offset` 大于 0 时追加到已有内容之后。
+/// 中途出错时保留已写入的内容，返回文件的总长度
+async fn write_part(
+    mut response: Response,
+    url: &str,
+    part_path: &Path,
+    offset: u64,
+    total: Option<u64>,
+    stall: StallTimeout,
+    progress: Option<&ProgressFn>,
+) -> Result<u64, Error> {
+    let mut file = if offset > 0 {
+        OpenOptions::new().append(true).open(part_path).await?
+    } else {
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(part_path)
+            .await?
+    };
+
+    let mut downloaded = offset;
+    let result: Result<(), Error> = async {
+        let mut window_start = Instant::now();
+        let mut window_bytes = 0;
+        loop {
+            let chunk = match timeout(stall.window, response.chunk()).await {
+                Ok(chunk) => chunk?,
+                Err(_) => return Err(stalled(url, window_bytes, stall)),
+            };
+            let Some(chunk) = chunk else {
+                break;
+            };
+            file.write_all(&chunk).await?;
+            downloaded += chunk.len() as u64;
+            if let Some(progress) = progress {
+                progress(Progress { downloaded, total });
+            }
+
+            window_bytes += chunk.len() as u64;
+            if window_start.elapsed() >= stall.window {
+                if window_bytes < stall.min_bytes() {
+                    return Err(stalled(url, window_bytes, stall));
+                }
+                window_start = Instant::now();
+                window_bytes = 0;
+            }
+        }
+        Ok(())
+    }
+    .await;
+    file.flush().await?;
+    result?;
+    file.sync_all().await?;
+
+    Ok(downloaded)
+}
+
+/// 可以重试，重试时从断点继续
+fn stalled(url: &str, received: u64, stall: StallTimeout) -> Error {
+    interrupted(
+        url,
+        format!(
+            "下载停滞: {:?} 内只收到 {} 字节，至少需要 {} 字节",
+            stall.window,
+            received,
+            stall.min_bytes()
+        ),
+    )
+}
+
+/// 以重命名的方式原子地替换目标文件
+async fn persist(part_path: &Path, to_path: &Path) -> Result<(), Error> {
+    tokio::fs::rename(part_path, to_path).await?;
+    #[cfg(unix)]
+    if let Some(dir) = to_path.parent() {
+        tokio::fs::File::open(dir).await?.sync_all().await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,207 @@
+use crate::InvalidImage;
+use reqwest::StatusCode;
+use std::error::Error as StdError;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// 获取、下载与应用壁纸过程中的错误，按类型区分以便决定是否重试、如何提示与退出码
+#[derive(Debug, thiserror::Error)]
+pub enum WallpaperError {
+    /// 连接失败、超时、连接中断等
+    #[error("网络请求失败: {source}")]
+    Network {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("{url} 返回 {status}")]
+    HttpStatus { url: String, status: StatusCode },
+    /// 下载停滞或不完整，重试时从断点继续
+    #[error("下载中断 {url}: {reason}")]
+    Interrupted { url: String, reason: String },
+    #[error("解析 {what} 失败: {source}")]
+    Parse {
+        what: String,
+        #[source]
+        source: BoxError,
+    },
+    /// 来源没有返回可用的壁纸
+    #[error("没有可用的壁纸: {0}")]
+    EmptyFeed(String),
+    #[error(transparent)]
+    InvalidImage(#[from] InvalidImage),
+    #[error("{}{source}", path_prefix(.path))]
+    Io {
+        path: Option<PathBuf>,
+        #[source]
+        source: io::Error,
+    },
+    #[error("{setter} 无法设置壁纸 {}: {source}", .path.display())]
+    ApplyFailed {
+        setter: &'static str,
+        path: PathBuf,
+        #[source]
+        source: Box<WallpaperError>,
+    },
+    /// 当前平台或桌面环境不支持
+    #[error("{0}")]
+    Unsupported(String),
+    /// D-Bus、Win32 或外部命令调用失败
+    #[error("系统接口调用失败: {0}")]
+    Platform(String),
+    /// 配置文件或命令行参数无效
+    #[error("{0}")]
+    Config(String),
+}
+
+fn path_prefix(path: &Option<PathBuf>) -> String {
+    path.as_ref()
+        .map(|path| format!("{}: ", path.display()))
+        .unwrap_or_default()
+}
+
+impl WallpaperError {
+    /// 用于 `map_err`，附带出错的路径
+    pub fn io(path: impl AsRef<Path>) -> impl FnOnce(io::Error) -> Self {
+        let path = path.as_ref().to_path_buf();
+        move |source| Self::Io {
+            path: Some(path),
+            source,
+        }
+    }
+
+    /// 用于 `map_err`，`what` 为解析的对象，例如文件路径
+    pub fn parse<E: Into<BoxError>>(what: impl Into<String>) -> impl FnOnce(E) -> Self {
+        let what = what.into();
+        move |source| Self::Parse {
+            what,
+            source: source.into(),
+        }
+    }
+
+    /// 超时、连接失败、连接中断、5xx 与 429 可以重试，其余（如 404、JSON 解析失败）不重试
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Network { source, .. } => {
+                if source.is_builder() || source.is_redirect() {
+                    return false;
+                }
+                if source.is_timeout() || source.is_connect() || source.is_body() {
+                    return true;
+                }
+                let mut cause = source.source();
+                while let Some(e) = cause {
+                    if let Some(e) = e.downcast_ref::<io::Error>() {
+                        return is_transient_io(e);
+                    }
+                    cause = e.source();
+                }
+                false
+            }
+            Self::HttpStatus { status, .. } => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            Self::Interrupted { .. } => true,
+            Self::Io { source, .. } => is_transient_io(source),
+            _ => false,
+        }
+    }
+
+    /// 网络不可用或服务端故障（5xx 与 429），稍后通常会自行恢复；404 等客户端错误不算
+    pub fn is_network(&self) -> bool {
+        match self {
+            Self::Network { .. } | Self::Interrupted { .. } => true,
+            Self::HttpStatus { status, .. } => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            _ => false,
+        }
+    }
+
+    /// 命令行的退出码，便于脚本判断失败原因
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::Config(_) => 2,
+            Self::Network { .. } | Self::Interrupted { .. } => 3,
+            Self::HttpStatus { .. } if self.is_network() => 3,
+            Self::Parse { .. } | Self::EmptyFeed(_) => 4,
+            Self::InvalidImage(_) => 5,
+            Self::ApplyFailed { .. } | Self::Unsupported(_) | Self::Platform(_) => 6,
+            Self::Io { .. } => 7,
+            Self::HttpStatus { .. } => 8,
+        }
+    }
+}
+
+fn is_transient_io(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::Interrupted
+    )
+}
+
+impl From<reqwest::Error> for WallpaperError {
+    fn from(source: reqwest::Error) -> Self {
+        let url = source.url().map(|url| url.to_string()).unwrap_or_default();
+        match source.status() {
+            Some(status) => Self::HttpStatus { url, status },
+            None => Self::Network { url, source },
+        }
+    }
+}
+
+impl From<io::Error> for WallpaperError {
+    fn from(source: io::Error) -> Self {
+        Self::Io { path: None, source }
+    }
+}
+
+impl From<tempfile::PersistError> for WallpaperError {
+    fn from(e: tempfile::PersistError) -> Self {
+        e.error.into()
+    }
+}
+
+impl From<url::ParseError> for WallpaperError {
+    fn from(source: url::ParseError) -> Self {
+        Self::Parse {
+            what: "地址".to_string(),
+            source: source.into(),
+        }
+    }
+}
+
+impl From<serde_json::Error> for WallpaperError {
+    fn from(source: serde_json::Error) -> Self {
+        if let Some(kind) = source.io_error_kind() {
+            return io::Error::new(kind, source).into();
+        }
+        Self::Parse {
+            what: "JSON".to_string(),
+            source: source.into(),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<zbus::Error> for WallpaperError {
+    fn from(e: zbus::Error) -> Self {
+        Self::Platform(e.to_string())
+    }
+}
+
+#[cfg(windows)]
+impl From<windows::core::Error> for WallpaperError {
+    fn from(e: windows::core::Error) -> Self {
+        Self::Platform(e.to_string())
+    }
+}
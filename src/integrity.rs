@@ -0,0 +1,97 @@
+use image::ImageFormat;
+use image::ImageReader;
+use reqwest::Response;
+use reqwest::header::CONTENT_TYPE;
+use std::fmt;
+use std::io::Cursor;
+use std::path::Path;
+
+/// 图片未通过校验，不应被应用为壁纸
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidImage(pub String);
+
+impl fmt::Display for InvalidImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "图片校验失败: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidImage {}
+
+/// 下载图片的校验要求，尺寸不区分横竖
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageCheck {
+    pub min_width: u32,
+    pub min_height: u32,
+}
+
+impl Default for ImageCheck {
+    fn default() -> Self {
+        Self {
+            min_width: 640,
+            min_height: 360,
+        }
+    }
+}
+
+impl ImageCheck {
+    /// 缺少 `Content-Type` 时交给解码判断
+    pub fn check_response(&self, response: &Response) -> Result<(), InvalidImage> {
+        let Some(content_type) = response.headers().get(CONTENT_TYPE) else {
+            return Ok(());
+        };
+        let content_type = content_type.to_str().unwrap_or_default();
+        if !content_type
+            .trim_start()
+            .to_ascii_lowercase()
+            .starts_with("image/")
+        {
+            return Err(InvalidImage(format!(
+                "Content-Type 不是图片: {}",
+                content_type
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// 完整解码图片，返回宽高
+    pub fn check_file(&self, path: &Path) -> Result<(u32, u32), InvalidImage> {
+        let bytes =
+            std::fs::read(path).map_err(|e| InvalidImage(format!("无法读取图片: {}", e)))?;
+        let reader = ImageReader::new(Cursor::new(&bytes))
+            .with_guessed_format()
+            .map_err(|e| InvalidImage(format!("无法读取图片: {}", e)))?;
+        // JPEG 解码器会容忍截断的数据，需要单独检查结束标记
+        if reader.format() == Some(ImageFormat::Jpeg) && !has_jpeg_end(&bytes) {
+            return Err(InvalidImage(
+                "JPEG 缺少结束标记，文件可能不完整".to_string(),
+            ));
+        }
+        let image = reader
+            .decode()
+            .map_err(|e| InvalidImage(format!("无法解码图片: {}", e)))?;
+        let (width, height) = (image.width(), image.height());
+        let (long, short) = (width.max(height), width.min(height));
+        let (min_long, min_short) = (
+            self.min_width.max(self.min_height),
+            self.min_width.min(self.min_height),
+        );
+        if long < min_long || short < min_short {
+            return Err(InvalidImage(format!(
+                "图片尺寸过小: {}x{}，至少为 {}x{}",
+                width, height, self.min_width, self.min_height
+            )));
+        }
+
+        Ok((width, height))
+    }
+}
+
+fn has_jpeg_end(bytes: &[u8]) -> bool {
+    let end = bytes
+        .iter()
+        .rposition(|&b| b != 0 && !b.is_ascii_whitespace())
+        .map_or(0, |i| i + 1);
+    bytes[..end].ends_with(&[0xFF, 0xD9])
+}
@@ -0,0 +1,33 @@
+use crate::Error;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::io;
+use std::path::Path;
+
+/// 文件不存在时返回 `None`
+pub(crate) fn read_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, Error> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(path)?;
+    Ok(Some(serde_json::from_str(&content)?))
+}
+
+/// 先写入同目录的临时文件并同步到磁盘，再重命名替换，中途退出不会留下不完整的文件
+pub(crate) fn write_json_atomic<T: Serialize + ?Sized>(
+    path: &Path,
+    value: &T,
+) -> Result<(), Error> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| Error::io(path)(io::ErrorKind::InvalidInput.into()))?;
+    std::fs::create_dir_all(dir)?;
+
+    let mut file = tempfile::NamedTempFile::new_in(dir)?;
+    serde_json::to_writer_pretty(&mut file, value)?;
+    file.as_file().sync_all()?;
+    file.persist(path)?;
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+mod archive;
+mod bing;
+mod cache;
+mod config;
+mod download;
+mod error;
+mod integrity;
+mod json_file;
+mod resolution;
+mod retry;
+mod scheduler;
+mod service;
+pub mod shell;
+mod source;
+mod state;
+pub mod wallpaper;
+
+pub use archive::Archive;
+pub use archive::ArchiveEntry;
+pub use archive::Retention;
+pub use bing::HpImage;
+pub use bing::HpJson;
+pub use bing::HpRequest;
+pub use bing::HpRequestBuilder;
+pub use bing::Market;
+pub use cache::HttpCache;
+pub use cache::Validators;
+pub use config::ApodConfig;
+pub use config::ArchiveConfig;
+pub use config::BingConfig;
+pub use config::Config;
+pub use config::ImageConfig;
+pub use config::LocalConfig;
+pub use config::LogConfig;
+pub use config::MenuConfig;
+pub use config::NetworkConfig;
+pub use config::RetryConfig;
+pub use config::SourceConfig;
+pub use config::UnsplashConfig;
+pub use config::UpdateConfig;
+pub use config::WikimediaConfig;
+pub use config::watch_config;
+pub use download::Downloader;
+pub use download::Progress;
+pub use download::ProgressFn;
+pub use download::StallTimeout;
+pub use download::Timeouts;
+pub use error::WallpaperError;
+pub use integrity::ImageCheck;
+pub use integrity::InvalidImage;
+pub use resolution::Resolution;
+pub use retry::RetryPolicy;
+pub use scheduler::Fire;
+pub use scheduler::Schedule;
+pub use scheduler::Scheduler;
+pub use scheduler::Trigger;
+pub use scheduler::system_events;
+pub use service::AppliedFn;
+pub use service::BackfillReport;
+pub use service::WallpaperService;
+pub use source::ApodSource;
+pub use source::BingSource;
+pub use source::ImageMeta;
+pub use source::ImageSource;
+pub use source::LocalSource;
+pub use source::SlideshowOrder;
+pub use source::UnsplashSource;
+pub use source::WikimediaSource;
+pub use state::State;
+pub use state::StateStore;
+pub use wallpaper::WallpaperSetter;
+
+pub type Error = WallpaperError;
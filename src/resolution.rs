@@ -0,0 +1,164 @@
+use crate::Error;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Resolution {
+    Uhd,
+    Size { width: u32, height: u32 },
+}
+
+const fn size(width: u32, height: u32) -> Resolution {
+    Resolution::Size { width, height }
+}
+
+impl Resolution {
+    pub const ALL: &[Resolution] = &[
+        Resolution::Uhd,
+        size(1920, 1200),
+        size(1920, 1080),
+        size(1366, 768),
+        size(1280, 768),
+        size(1024, 768),
+        size(800, 600),
+        size(800, 480),
+        size(640, 480),
+        size(400, 240),
+        size(320, 240),
+        size(1080, 1920),
+        size(768, 1366),
+        size(768, 1280),
+        size(720, 1280),
+        size(480, 800),
+        size(240, 320),
+    ];
+
+    pub fn width(&self) -> u32 {
+        match self {
+            Self::Uhd => 3840,
+            Self::Size { width, .. } => *width,
+        }
+    }
+
+    pub fn height(&self) -> u32 {
+        match self {
+            Self::Uhd => 2160,
+            Self::Size { height, .. } => *height,
+        }
+    }
+
+    pub fn is_portrait(&self) -> bool {
+        self.height() > self.width()
+    }
+
+    fn area(&self) -> u64 {
+        u64::from(self.width()) * u64::from(self.height())
+    }
+
+    /// 选择能覆盖屏幕的最小分辨率，没有则选择同方向最大的分辨率
+    pub fn for_screen(width: u32, height: u32) -> Self {
+        let portrait = height > width;
+        let mut candidates: Vec<Resolution> = Self::ALL
+            .iter()
+            .copied()
+            .filter(|r| r.is_portrait() == portrait)
+            .collect();
+        candidates.sort_by_key(|r| r.area());
+
+        candidates
+            .iter()
+            .copied()
+            .find(|r| r.width() >= width && r.height() >= height)
+            .or_else(|| candidates.last().copied())
+            .unwrap_or(Self::Uhd)
+    }
+
+    pub fn detect() -> Option<Self> {
+        let (width, height) = screen_size()?;
+        let resolution = Self::for_screen(width, height);
+        tracing::info!("屏幕分辨率: {}x{}，使用 {}", width, height, resolution);
+        Some(resolution)
+    }
+
+    /// 自身在前，其后为同方向、面积更小的分辨率，由大到小排列
+    pub fn ranked(&self) -> Vec<Resolution> {
+        let mut smaller: Vec<Resolution> = Self::ALL
+            .iter()
+            .copied()
+            .filter(|r| r.is_portrait() == self.is_portrait() && r.area() < self.area())
+            .collect();
+        smaller.sort_by_key(|r| std::cmp::Reverse(r.area()));
+
+        let mut ranked = vec![*self];
+        ranked.extend(smaller);
+        ranked
+    }
+
+    pub fn image_url(&self, urlbase: &str) -> String {
+        format!("{}_{}.jpg", urlbase, self)
+    }
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Uhd => f.write_str("UHD"),
+            Self::Size { width, height } => write!(f, "{}x{}", width, height),
+        }
+    }
+}
+
+impl FromStr for Resolution {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|r| r.to_string().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| Error::Config(format!("不支持的分辨率: {}", s)))
+    }
+}
+
+#[cfg(windows)]
+fn screen_size() -> Option<(u32, u32)> {
+    use windows::Win32::Graphics::Gdi::DEVMODEW;
+    use windows::Win32::Graphics::Gdi::ENUM_CURRENT_SETTINGS;
+    use windows::Win32::Graphics::Gdi::EnumDisplaySettingsW;
+    use windows::core::PCWSTR;
+
+    let mut devmode = DEVMODEW {
+        dmSize: std::mem::size_of::<DEVMODEW>() as u16,
+        ..Default::default()
+    };
+    let ok = unsafe { EnumDisplaySettingsW(PCWSTR::null(), ENUM_CURRENT_SETTINGS, &mut devmode) };
+    if !ok.as_bool() || devmode.dmPelsWidth == 0 || devmode.dmPelsHeight == 0 {
+        return None;
+    }
+
+    Some((devmode.dmPelsWidth, devmode.dmPelsHeight))
+}
+
+#[cfg(target_os = "linux")]
+fn screen_size() -> Option<(u32, u32)> {
+    std::fs::read_dir("/sys/class/drm")
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            std::fs::read_to_string(path.join("status"))
+                .is_ok_and(|status| status.trim() == "connected")
+        })
+        .filter_map(|path| {
+            let modes = std::fs::read_to_string(path.join("modes")).ok()?;
+            let (width, height) = modes.lines().next()?.split_once('x')?;
+            let height = height.trim_end_matches(|c: char| !c.is_ascii_digit());
+            Some((width.parse().ok()?, height.parse().ok()?))
+        })
+        .max_by_key(|&(width, height): &(u32, u32)| u64::from(width) * u64::from(height))
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn screen_size() -> Option<(u32, u32)> {
+    None
+}
@@ -0,0 +1,88 @@
+use crate::Error;
+use std::future::Future;
+use std::time::Duration;
+use tracing::info;
+use tracing::warn;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// 第 `attempt` 次失败后的等待时间，`attempt` 从 1 开始
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31);
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+        if !self.jitter {
+            return delay;
+        }
+
+        let half = delay / 2;
+        half + half.mul_f64(fastrand::f64())
+    }
+
+    pub async fn retry<T, F, Fut>(&self, operation: &str, mut f: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let max_attempts = self.max_attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            let e = match f().await {
+                Ok(value) => {
+                    if attempt > 1 {
+                        info!("{}成功，共尝试 {} 次", operation, attempt);
+                    }
+                    return Ok(value);
+                }
+                Err(e) => e,
+            };
+
+            if !e.is_retryable() {
+                warn!("{}失败，错误不可重试: {:?}", operation, e);
+                return Err(e);
+            }
+            if attempt >= max_attempts {
+                warn!(
+                    "{}失败，已达到最大尝试次数 {}: {:?}",
+                    operation, max_attempts, e
+                );
+                return Err(e);
+            }
+
+            let delay = self.delay(attempt);
+            warn!(
+                "{}失败 ({}/{})，{:?} 后重试: {:?}",
+                operation, attempt, max_attempts, delay, e
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tokio::time::sleep_until;
+use tracing::info;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    Resume,
+    NetworkOnline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fire {
+    Scheduled,
+    Trigger(Trigger),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Schedule {
+    /// 无法预计发布时间时的更新间隔
+    pub interval: Duration,
+    pub debounce: Duration,
+    pub on_resume: bool,
+    pub on_network: bool,
+    /// 根据必应的发布时间安排更新
+    pub smart: bool,
+    pub rollover_delay: Duration,
+    pub poll_interval: Duration,
+    pub poll_window: Duration,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60 * 60),
+            debounce: Duration::from_secs(10),
+            on_resume: true,
+            on_network: true,
+            smart: true,
+            rollover_delay: Duration::from_secs(60),
+            poll_interval: Duration::from_secs(5 * 60),
+            poll_window: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+impl Schedule {
+    /// 距离下次更新的时间：发布后稍等片刻再获取，新壁纸迟迟未出现时在 `poll_window` 内短间隔轮询，
+    /// 其余情况按 `interval` 更新
+    pub fn next_wake(&self, rollover: Option<OffsetDateTime>, now: OffsetDateTime) -> Duration {
+        let Some(rollover) = rollover.filter(|_| self.smart) else {
+            return self.interval;
+        };
+
+        let wake = rollover + self.rollover_delay;
+        if wake > now {
+            return Duration::try_from(wake - now).unwrap_or(self.interval);
+        }
+        if now < rollover + self.poll_window {
+            return self.poll_interval;
+        }
+
+        self.interval
+    }
+
+    fn accepts(&self, trigger: Trigger) -> bool {
+        match trigger {
+            Trigger::Resume => self.on_resume,
+            Trigger::NetworkOnline => self.on_network,
+        }
+    }
+}
+
+/// 到达预定时间时触发，默认每隔 `interval` 一次，同时在收到系统事件（唤醒、联网）后防抖触发。
+/// 只依赖 tokio 的时钟，可以在测试中通过 `tokio::time::pause` 控制时间
+pub struct Scheduler {
+    schedule: Schedule,
+    deadline: Instant,
+    triggers: mpsc::Receiver<Trigger>,
+    triggers_open: bool,
+    pending: Option<(Trigger, Instant)>,
+}
+
+impl Scheduler {
+    pub fn new(schedule: Schedule, triggers: mpsc::Receiver<Trigger>) -> Self {
+        Self {
+            schedule,
+            deadline: Instant::now(),
+            triggers,
+            triggers_open: true,
+            pending: None,
+        }
+    }
+
+    pub fn schedule(&self) -> Schedule {
+        self.schedule
+    }
+
+    pub fn set_schedule(&mut self, schedule: Schedule) {
+        if schedule.interval != self.schedule.interval {
+            info!("更新间隔: {:?}", schedule.interval);
+            self.deadline = Instant::now() + schedule.interval;
+        }
+        if self
+            .pending
+            .is_some_and(|(trigger, _)| !schedule.accepts(trigger))
+        {
+            self.pending = None;
+        }
+        self.schedule = schedule;
+    }
+
+    pub fn fire_now(&mut self) {
+        self.deadline = Instant::now();
+    }
+
+    /// 将下一次预定触发安排在 `delay` 之后
+    pub fn wake_after(&mut self, delay: Duration) {
+        self.deadline = Instant::now() + delay;
+    }
+
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// 可以安全地在 `tokio::select!` 中取消
+    pub async fn next(&mut self) -> Fire {
+        loop {
+            let deadline = self.pending.map(|(_, deadline)| deadline);
+            tokio::select! {
+                _ = sleep_until(self.deadline) => {
+                    self.pending = None;
+                    self.deadline = Instant::now() + self.schedule.interval;
+                    return Fire::Scheduled;
+                }
+                trigger = self.triggers.recv(), if self.triggers_open => match trigger {
+                    Some(trigger) if self.schedule.accepts(trigger) => {
+                        info!("收到系统事件: {:?}", trigger);
+                        self.pending = Some((trigger, Instant::now() + self.schedule.debounce));
+                    }
+                    Some(_) => {}
+                    None => self.triggers_open = false,
+                },
+                _ = sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                    let (trigger, _) = self.pending.take().expect("pending trigger");
+                    self.deadline = Instant::now() + self.schedule.interval;
+                    return Fire::Trigger(trigger);
+                }
+            }
+        }
+    }
+}
+
+/// 监听系统唤醒与联网事件并发送到 `sender`，不会主动结束
+#[cfg(target_os = "linux")]
+pub async fn system_events(sender: mpsc::Sender<Trigger>) {
+    if let Err(e) = linux::watch(sender).await {
+        tracing::warn!("监听系统事件失败: {:?}", e);
+    }
+    std::future::pending().await
+}
+
+#[cfg(windows)]
+pub async fn system_events(sender: mpsc::Sender<Trigger>) {
+    let _registration = match windows::register(sender) {
+        Ok(registration) => Some(registration),
+        Err(e) => {
+            tracing::warn!("监听系统事件失败: {:?}", e);
+            None
+        }
+    };
+    std::future::pending().await
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+pub async fn system_events(_sender: mpsc::Sender<Trigger>) {
+    std::future::pending().await
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::Trigger;
+    use crate::Error;
+    use futures_util::StreamExt;
+    use tokio::sync::mpsc;
+    use zbus::Connection;
+    use zbus::MatchRule;
+    use zbus::MessageStream;
+    use zbus::message::Type;
+
+    const NM_STATE_CONNECTED_GLOBAL: u32 = 70;
+
+    pub async fn watch(sender: mpsc::Sender<Trigger>) -> Result<(), Error> {
+        let connection = Connection::system().await?;
+        let (resume, network) = tokio::join!(
+            watch_resume(&connection, &sender),
+            watch_network(&connection, &sender)
+        );
+        resume.and(network)
+    }
+
+    async fn watch_resume(
+        connection: &Connection,
+        sender: &mpsc::Sender<Trigger>,
+    ) -> Result<(), Error> {
+        let rule = MatchRule::builder()
+            .msg_type(Type::Signal)
+            .sender("org.freedesktop.login1")?
+            .interface("org.freedesktop.login1.Manager")?
+            .member("PrepareForSleep")?
+            .build();
+        let mut stream = MessageStream::for_match_rule(rule, connection, None).await?;
+        while let Some(message) = stream.next().await {
+            let sleeping: bool = message?.body().deserialize()?;
+            if !sleeping && sender.send(Trigger::Resume).await.is_err() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn watch_network(
+        connection: &Connection,
+        sender: &mpsc::Sender<Trigger>,
+    ) -> Result<(), Error> {
+        let rule = MatchRule::builder()
+            .msg_type(Type::Signal)
+            .sender("org.freedesktop.NetworkManager")?
+            .interface("org.freedesktop.NetworkManager")?
+            .member("StateChanged")?
+            .build();
+        let mut stream = MessageStream::for_match_rule(rule, connection, None).await?;
+        let mut last_state = None;
+        while let Some(message) = stream.next().await {
+            let state: u32 = message?.body().deserialize()?;
+            let online = state == NM_STATE_CONNECTED_GLOBAL;
+            let was_online = last_state == Some(NM_STATE_CONNECTED_GLOBAL);
+            last_state = Some(state);
+            if online && !was_online && sender.send(Trigger::NetworkOnline).await.is_err() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use super::Trigger;
+    use crate::Error;
+    use std::ffi::c_void;
+    use tokio::sync::mpsc;
+    use windows::Win32::Foundation::HANDLE;
+    use windows::Win32::System::Power::DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS;
+    use windows::Win32::System::Power::HPOWERNOTIFY;
+    use windows::Win32::System::Power::PowerRegisterSuspendResumeNotification;
+    use windows::Win32::System::Power::PowerUnregisterSuspendResumeNotification;
+    use windows::Win32::UI::WindowsAndMessaging::DEVICE_NOTIFY_CALLBACK;
+    use windows::Win32::UI::WindowsAndMessaging::PBT_APMRESUMEAUTOMATIC;
+
+    pub struct Registration {
+        handle: HPOWERNOTIFY,
+        parameters: *mut DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS,
+    }
+
+    // 回调只通过 `mpsc::Sender::try_send` 访问上下文
+    unsafe impl Send for Registration {}
+
+    impl Drop for Registration {
+        fn drop(&mut self) {
+            unsafe {
+                let _ = PowerUnregisterSuspendResumeNotification(self.handle);
+                let parameters = Box::from_raw(self.parameters);
+                drop(Box::from_raw(
+                    parameters.Context as *mut mpsc::Sender<Trigger>,
+                ));
+            }
+        }
+    }
+
+    unsafe extern "system" fn on_power_event(
+        context: *const c_void,
+        event: u32,
+        _setting: *const c_void,
+    ) -> u32 {
+        if event == PBT_APMRESUMEAUTOMATIC {
+            let sender = unsafe { &*(context as *const mpsc::Sender<Trigger>) };
+            let _ = sender.try_send(Trigger::Resume);
+        }
+        0
+    }
+
+    pub fn register(sender: mpsc::Sender<Trigger>) -> Result<Registration, Error> {
+        let context = Box::into_raw(Box::new(sender)) as *mut c_void;
+        let parameters = Box::into_raw(Box::new(DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS {
+            Callback: Some(on_power_event),
+            Context: context,
+        }));
+        let mut handle = std::ptr::null_mut();
+        let result = unsafe {
+            PowerRegisterSuspendResumeNotification(
+                DEVICE_NOTIFY_CALLBACK,
+                HANDLE(parameters as *mut c_void),
+                &mut handle,
+            )
+        };
+        if result.is_err() {
+            unsafe {
+                drop(Box::from_raw(parameters));
+                drop(Box::from_raw(context as *mut mpsc::Sender<Trigger>));
+            }
+            return Err(Error::Platform(format!(
+                "注册休眠唤醒通知失败: {:?}",
+                result
+            )));
+        }
+
+        Ok(Registration {
+            handle: HPOWERNOTIFY(handle as isize),
+            parameters,
+        })
+    }
+}
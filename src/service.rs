@@ -0,0 +1,556 @@
+use crate::Archive;
+use crate::BingSource;
+use crate::Config;
+use crate::Downloader;
+use crate::Error;
+use crate::Fire;
+use crate::HpRequest;
+use crate::HttpCache;
+use crate::ImageCheck;
+use crate::ImageMeta;
+use crate::ImageSource;
+use crate::ProgressFn;
+use crate::RetryPolicy;
+use crate::Schedule;
+use crate::Scheduler;
+use crate::State;
+use crate::StateStore;
+use crate::Timeouts;
+use crate::Trigger;
+use crate::WallpaperSetter;
+use crate::system_events;
+use crate::wallpaper::detect_setter;
+use ::time::Date;
+use ::time::OffsetDateTime;
+use reqwest::Client;
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::sync::watch;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+/// 必应接口最多返回最近约 15 天的壁纸
+const BACKFILL_COUNT: usize = 16;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BackfillReport {
+    pub downloaded: Vec<PathBuf>,
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+/// 新壁纸应用并记录后调用，参数为壁纸信息与图片路径
+pub type AppliedFn = Arc<dyn Fn(&ImageMeta, &Path) + Send + Sync>;
+
+#[derive(Clone)]
+pub struct WallpaperService {
+    downloader: Downloader,
+    source: Arc<dyn ImageSource>,
+    schedule: Schedule,
+    setter: Arc<dyn WallpaperSetter>,
+    state: StateStore,
+    archive: Archive,
+    backfill: bool,
+    /// 为空时补全当前的壁纸来源
+    backfill_sources: Vec<Arc<dyn ImageSource>>,
+    on_applied: Option<AppliedFn>,
+}
+
+impl WallpaperService {
+    pub fn new() -> Result<Self, Error> {
+        Self::from_config(&Config::default())
+    }
+
+    pub fn from_config(config: &Config) -> Result<Self, Error> {
+        let mut service = Self::with_downloader(Downloader::from_config(config)?);
+        service.source = config.source()?;
+        service.schedule = config.schedule();
+        service.archive = config.archive();
+        service.backfill = config.archive.backfill;
+        service.backfill_sources = config.bing_sources(&config.archive.markets)?;
+
+        Ok(service)
+    }
+
+    pub fn with_client(client: Client) -> Self {
+        Self::with_downloader(Downloader::new(client))
+    }
+
+    pub fn with_downloader(downloader: Downloader) -> Self {
+        let config = Config::default();
+        Self {
+            downloader,
+            source: Arc::new(BingSource::new(HpRequest::default(), None)),
+            schedule: config.schedule(),
+            setter: detect_setter(),
+            state: StateStore::in_memory(),
+            archive: Archive::new(Archive::default_dir()),
+            backfill: false,
+            backfill_sources: Vec::new(),
+            on_applied: None,
+        }
+    }
+
+    pub fn reconfigure(&mut self, config: &Config) -> Result<(), Error> {
+        self.downloader.reconfigure(config)?;
+        self.source = config.source()?;
+        self.schedule = config.schedule();
+        self.archive = config.archive();
+        self.backfill = config.archive.backfill;
+        self.backfill_sources = config.bing_sources(&config.archive.markets)?;
+
+        Ok(())
+    }
+
+    /// `config` 收到新配置时重新配置，返回是否有变化
+    pub fn reconfigure_if_changed(
+        &mut self,
+        config: &mut watch::Receiver<Arc<Config>>,
+    ) -> Result<bool, Error> {
+        if !config.has_changed().unwrap_or(false) {
+            return Ok(false);
+        }
+
+        let config = config.borrow_and_update().clone();
+        self.reconfigure(&config)?;
+        Ok(true)
+    }
+
+    pub fn with_source(mut self, source: Arc<dyn ImageSource>) -> Self {
+        self.source = source;
+        self
+    }
+
+    pub fn with_schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    pub fn with_state(mut self, state: StateStore) -> Self {
+        self.state = state;
+        self
+    }
+
+    pub fn with_cache(mut self, cache: HttpCache) -> Self {
+        self.downloader = self.downloader.with_cache(cache);
+        self
+    }
+
+    pub fn with_image_check(mut self, check: ImageCheck) -> Self {
+        self.downloader = self.downloader.with_image_check(check);
+        self
+    }
+
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.downloader = self.downloader.with_timeouts(timeouts);
+        self
+    }
+
+    /// 定时更新时补全壁纸库，`sources` 为空时补全当前的壁纸来源
+    pub fn with_backfill(mut self, sources: Vec<Arc<dyn ImageSource>>) -> Self {
+        self.backfill = true;
+        self.backfill_sources = sources;
+        self
+    }
+
+    pub fn with_on_applied(mut self, on_applied: AppliedFn) -> Self {
+        self.on_applied = Some(on_applied);
+        self
+    }
+
+    pub fn with_progress(mut self, progress: ProgressFn) -> Self {
+        self.downloader = self.downloader.with_progress(progress);
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.downloader = self.downloader.with_retry(retry);
+        self
+    }
+
+    pub fn with_archive(mut self, archive: Archive) -> Self {
+        self.downloader = self.downloader.with_archive(archive.clone());
+        self.archive = archive;
+        self
+    }
+
+    pub fn with_setter(mut self, setter: Arc<dyn WallpaperSetter>) -> Self {
+        self.setter = setter;
+        self
+    }
+
+    pub fn client(&self) -> &Client {
+        self.downloader.client()
+    }
+
+    pub fn downloader(&self) -> &Downloader {
+        &self.downloader
+    }
+
+    pub fn source(&self) -> &Arc<dyn ImageSource> {
+        &self.source
+    }
+
+    pub fn setter(&self) -> &Arc<dyn WallpaperSetter> {
+        &self.setter
+    }
+
+    pub fn archive(&self) -> &Archive {
+        &self.archive
+    }
+
+    pub fn state(&self) -> &StateStore {
+        &self.state
+    }
+
+    pub fn cache(&self) -> &HttpCache {
+        self.downloader.cache()
+    }
+
+    pub fn backfill_sources(&self) -> Vec<Arc<dyn ImageSource>> {
+        if self.backfill_sources.is_empty() {
+            return vec![self.source.clone()];
+        }
+
+        self.backfill_sources.clone()
+    }
+
+    pub async fn handle_enable_daily_updating(self, config: watch::Receiver<Arc<Config>>) {
+        let (sender, triggers) = mpsc::channel(8);
+        tokio::select! {
+            _ = self.run_daily_updating(config, triggers) => {}
+            _ = system_events(sender) => {}
+        }
+    }
+
+    /// 按计划更新壁纸，系统事件由 `triggers` 提供
+    pub async fn run_daily_updating(
+        mut self,
+        mut config: watch::Receiver<Arc<Config>>,
+        triggers: mpsc::Receiver<Trigger>,
+    ) {
+        let mut scheduler = Scheduler::new(self.schedule, triggers);
+        let mut watching = true;
+        let mut last_backfill = None;
+        loop {
+            tokio::select! {
+                fire = scheduler.next() => {
+                    if let Fire::Trigger(trigger) = fire {
+                        info!("系统事件触发更新: {:?}", trigger);
+                    }
+                    match self.update_wallpaper().await {
+                        Ok(()) => self.backfill_daily(&mut last_backfill).await,
+                        Err(e) => log_update_error(&e),
+                    }
+
+                    let wake = self.next_wake().await;
+                    info!("下次更新: {:?} 后", wake);
+                    scheduler.wake_after(wake);
+                }
+                changed = config.changed(), if watching => {
+                    if changed.is_err() {
+                        watching = false;
+                        continue;
+                    }
+
+                    let last_source = self.source.key();
+                    let last_interval = self.source.interval();
+                    let config = config.borrow_and_update().clone();
+                    if let Err(e) = self.reconfigure(&config) {
+                        error!("应用配置失败: {:?}", e);
+                        continue;
+                    }
+
+                    let last_schedule = scheduler.schedule();
+                    scheduler.set_schedule(self.schedule);
+                    if self.source.key() != last_source {
+                        scheduler.fire_now();
+                    } else if self.schedule != last_schedule
+                        || self.source.interval() != last_interval
+                    {
+                        scheduler.wake_after(self.next_wake().await);
+                    }
+                }
+            }
+        }
+    }
+
+    /// 根据当前壁纸推算下一张壁纸的发布时间，来源有自己的更新间隔时使用该间隔
+    pub async fn next_wake(&self) -> Duration {
+        if let Some(interval) = self.source.interval() {
+            return interval;
+        }
+
+        let rollover = self
+            .state
+            .get()
+            .await
+            .image
+            .filter(|image| image.source == self.source.name())
+            .and_then(|image| self.source.next_rollover(&image));
+        self.schedule.next_wake(rollover, OffsetDateTime::now_utc())
+    }
+
+    /// 每天最多补全一次，避免每次轮询与系统事件都重新获取壁纸列表
+    async fn backfill_daily(&self, last_backfill: &mut Option<Date>) {
+        let today = OffsetDateTime::now_utc().date();
+        if !self.backfill || *last_backfill == Some(today) {
+            return;
+        }
+        *last_backfill = Some(today);
+
+        if let Err(e) = self.backfill(&self.backfill_sources()).await {
+            error!("补全壁纸库失败: {:?}", e);
+        }
+    }
+
+    pub async fn handle_update_wallpaper(self) {
+        if let Err(e) = self.update_wallpaper().await {
+            log_update_error(&e);
+        }
+    }
+
+    pub async fn update_wallpaper(&self) -> Result<(), Error> {
+        info!("开始更新壁纸: {}", self.source.name());
+
+        // 只有已应用过壁纸时才能信任 304
+        let key = self.source.key();
+        let cached = match self.state.get().await.image {
+            Some(_) => self.cache().metadata(&key).await,
+            None => None,
+        };
+        let Some((latest_image, validators)) = self
+            .source
+            .latest_if_modified(&self.downloader, cached.as_ref())
+            .await?
+        else {
+            info!("壁纸信息未变化，跳过更新");
+            return Ok(());
+        };
+        info!("更新链接: {}", latest_image.url());
+
+        if self.check_needed_update(&latest_image).await {
+            let latest_image_path = self.download_wallpaper(&latest_image).await?;
+
+            self.apply_wallpaper(&latest_image_path).await?;
+
+            self.record_update(&latest_image, latest_image_path.clone())
+                .await?;
+
+            if let Some(on_applied) = &self.on_applied {
+                on_applied(&latest_image, &latest_image_path);
+            }
+        }
+
+        // 选中的图片已是当前壁纸时同样记录，避免轮换停在同一张
+        if let Err(e) = self.source.applied(&latest_image) {
+            warn!("记录轮换进度失败: {:?}", e);
+        }
+
+        // 更新成功后再记录，避免失败后因 304 不再重试
+        if !validators.is_empty()
+            && let Err(e) = self.cache().set_metadata(&key, validators).await
+        {
+            warn!("保存 HTTP 缓存失败: {:?}", e);
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_latest_image(&self) -> Result<ImageMeta, Error> {
+        let latest_image = self.source.latest(&self.downloader).await?;
+
+        info!("更新链接: {}", latest_image.url());
+
+        Ok(latest_image)
+    }
+
+    /// 补全壁纸库中缺失的最近壁纸，按来源与 id 去重，不应用壁纸
+    pub async fn backfill(
+        &self,
+        sources: &[Arc<dyn ImageSource>],
+    ) -> Result<BackfillReport, Error> {
+        info!("开始补全壁纸库");
+
+        let mut report = BackfillReport::default();
+        let mut seen: HashSet<String> = self
+            .archive
+            .entries()?
+            .into_iter()
+            .filter_map(|entry| entry.image.key())
+            .collect();
+
+        for source in sources {
+            if !source.archives() {
+                info!("{} 不保存到壁纸库，跳过补全", source.name());
+                continue;
+            }
+
+            let images = match source.history(&self.downloader, BACKFILL_COUNT).await {
+                Ok(images) => images,
+                Err(e) => {
+                    warn!("获取壁纸列表失败 {}: {:?}", source.key(), e);
+                    report.failed += 1;
+                    continue;
+                }
+            };
+
+            for image in images {
+                let key = image
+                    .key()
+                    .unwrap_or_else(|| format!("{}:{}", image.label(), image.url()));
+                if !seen.insert(key) {
+                    report.skipped += 1;
+                    continue;
+                }
+                // 否则下载后立即被清理，之后每次补全都会重新下载
+                if self.archive.is_expired(&image) {
+                    info!("壁纸早于保留天数，跳过补全: {}", image.url());
+                    report.skipped += 1;
+                    continue;
+                }
+
+                match self.archive_image(source.as_ref(), &image).await {
+                    Ok(path) => report.downloaded.push(path),
+                    Err(e) => {
+                        warn!("补全壁纸失败 {}: {:?}", image.url(), e);
+                        report.failed += 1;
+                    }
+                }
+            }
+        }
+
+        info!(
+            "补全壁纸库完成，下载 {} 张，跳过 {} 张，失败 {} 次",
+            report.downloaded.len(),
+            report.skipped,
+            report.failed
+        );
+
+        Ok(report)
+    }
+
+    pub async fn check_needed_update(&self, latest_image: &ImageMeta) -> bool {
+        let state = self.state.get().await;
+        if state
+            .image
+            .is_some_and(|last_image| last_image.same_image(latest_image))
+        {
+            info!("更新链接相同，跳过更新");
+            return false;
+        }
+
+        true
+    }
+
+    async fn record_update(&self, image: &ImageMeta, path: PathBuf) -> Result<(), Error> {
+        self.state
+            .set(State {
+                image: Some(image.clone()),
+                path: Some(path),
+                updated_at: Some(
+                    OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc()),
+                ),
+            })
+            .await
+    }
+
+    pub async fn download_wallpaper(&self, image: &ImageMeta) -> Result<PathBuf, Error> {
+        self.archive_image(self.source.as_ref(), image).await
+    }
+
+    async fn archive_image(
+        &self,
+        source: &dyn ImageSource,
+        image: &ImageMeta,
+    ) -> Result<PathBuf, Error> {
+        if !source.archives() {
+            return source
+                .download(&self.downloader, image, self.archive.dir())
+                .await;
+        }
+
+        for image_url in &image.urls {
+            if let Some(entry) = self.archive.find(image, image_url)? {
+                let path = self.archive.dir().join(&entry.file);
+                if path.exists() {
+                    info!("壁纸已存在: {}", path.display());
+                    return Ok(path);
+                }
+            }
+        }
+
+        let path = source
+            .download(&self.downloader, image, self.archive.dir())
+            .await?;
+        self.archive.add(image, &path)?;
+
+        Ok(path)
+    }
+
+    pub async fn fetch_wallpaper(&self, out_dir: &Path) -> Result<PathBuf, Error> {
+        let latest_image = self.get_latest_image().await?;
+
+        let path = self
+            .source
+            .download(&self.downloader, &latest_image, out_dir)
+            .await?;
+        if self.source.archives() {
+            return Ok(path);
+        }
+
+        // 本地目录等来源直接返回原文件，需要复制到 `out_dir`
+        tokio::fs::create_dir_all(out_dir)
+            .await
+            .map_err(Error::io(out_dir))?;
+        let to_dir = tokio::fs::canonicalize(out_dir)
+            .await
+            .map_err(Error::io(out_dir))?;
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| Error::io(&path)(io::ErrorKind::InvalidInput.into()))?;
+        let to_path = to_dir.join(file_name);
+        if tokio::fs::canonicalize(&path)
+            .await
+            .map_err(Error::io(&path))?
+            == to_path
+        {
+            return Ok(path);
+        }
+        tokio::fs::copy(&path, &to_path)
+            .await
+            .map_err(Error::io(&to_path))?;
+        info!("保存壁纸: {}", to_path.display());
+
+        Ok(to_path)
+    }
+
+    /// 设置壁纸可能需要执行外部程序，在阻塞线程中进行
+    pub async fn apply_wallpaper(&self, path: &Path) -> Result<(), Error> {
+        let setter = self.setter.clone();
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            setter.set_wallpaper(&path).map_err(|e| Error::ApplyFailed {
+                setter: setter.name(),
+                path,
+                source: Box::new(e),
+            })
+        })
+        .await
+        .map_err(|e| Error::Platform(format!("设置壁纸的任务异常结束: {}", e)))?
+    }
+}
+
+fn log_update_error(e: &Error) {
+    if e.is_network() {
+        warn!("网络不可用，等待下次更新: {}", e);
+    } else {
+        error!("更新壁纸失败: {:?}", e);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::Error;
+use std::ffi::OsStr;
+
+/// 使用系统默认程序打开文件、目录或网址
+pub fn open(target: impl AsRef<OsStr>) -> Result<(), Error> {
+    platform::open(target.as_ref())
+}
+
+pub fn copy_text(text: &str) -> Result<(), Error> {
+    platform::copy_text(text)
+}
+
+#[cfg(windows)]
+mod platform {
+    use crate::Error;
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use windows::Win32::Foundation::GlobalFree;
+    use windows::Win32::Foundation::HANDLE;
+    use windows::Win32::System::DataExchange::CloseClipboard;
+    use windows::Win32::System::DataExchange::EmptyClipboard;
+    use windows::Win32::System::DataExchange::OpenClipboard;
+    use windows::Win32::System::DataExchange::SetClipboardData;
+    use windows::Win32::System::Memory::GMEM_MOVEABLE;
+    use windows::Win32::System::Memory::GlobalAlloc;
+    use windows::Win32::System::Memory::GlobalLock;
+    use windows::Win32::System::Memory::GlobalUnlock;
+    use windows::Win32::System::Ole::CF_UNICODETEXT;
+    use windows::Win32::UI::Shell::ShellExecuteW;
+    use windows::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
+    use windows::core::PCWSTR;
+    use windows::core::w;
+
+    fn wide(s: &OsStr) -> Vec<u16> {
+        s.encode_wide().chain(std::iter::once(0)).collect()
+    }
+
+    pub fn open(target: &OsStr) -> Result<(), Error> {
+        let file = wide(target);
+        let result = unsafe {
+            ShellExecuteW(
+                None,
+                w!("open"),
+                PCWSTR(file.as_ptr()),
+                PCWSTR::null(),
+                PCWSTR::null(),
+                SW_SHOWNORMAL,
+            )
+        };
+        // 返回值不大于 32 时表示错误码
+        if result.0 as usize <= 32 {
+            return Err(Error::Platform(format!(
+                "无法打开 {}: ShellExecuteW 返回 {}",
+                target.display(),
+                result.0 as usize
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn copy_text(text: &str) -> Result<(), Error> {
+        let text = wide(OsStr::new(text));
+        unsafe {
+            OpenClipboard(None)?;
+            let result = set_text(&text);
+            let _ = CloseClipboard();
+            result
+        }
+    }
+
+    /// 成功后内存由剪贴板接管
+    unsafe fn set_text(text: &[u16]) -> Result<(), Error> {
+        unsafe {
+            EmptyClipboard()?;
+            let memory = GlobalAlloc(GMEM_MOVEABLE, std::mem::size_of_val(text))?;
+            let target = GlobalLock(memory) as *mut u16;
+            if target.is_null() {
+                let _ = GlobalFree(Some(memory));
+                return Err(Error::Platform("无法分配剪贴板内存".to_string()));
+            }
+            std::ptr::copy_nonoverlapping(text.as_ptr(), target, text.len());
+            let _ = GlobalUnlock(memory);
+            if let Err(e) = SetClipboardData(CF_UNICODETEXT.0.into(), Some(HANDLE(memory.0))) {
+                let _ = GlobalFree(Some(memory));
+                return Err(e.into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(not(windows))]
+mod platform {
+    use crate::Error;
+    use std::ffi::OsStr;
+    use std::io::Write;
+    use std::process::Command;
+    use std::process::Stdio;
+
+    #[cfg(target_os = "macos")]
+    const OPENER: &str = "open";
+    #[cfg(not(target_os = "macos"))]
+    const OPENER: &str = "xdg-open";
+
+    /// 依次尝试，Wayland 优先
+    #[cfg(target_os = "macos")]
+    const CLIPBOARD: &[&[&str]] = &[&["pbcopy"]];
+    #[cfg(not(target_os = "macos"))]
+    const CLIPBOARD: &[&[&str]] = &[
+        &["wl-copy"],
+        &["xclip", "-selection", "clipboard"],
+        &["xsel", "--clipboard", "--input"],
+    ];
+
+    pub fn open(target: &OsStr) -> Result<(), Error> {
+        let status = Command::new(OPENER)
+            .arg(target)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map_err(Error::io(OPENER))?;
+        if !status.success() {
+            return Err(Error::Platform(format!(
+                "{} {} 执行失败 ({})",
+                OPENER,
+                target.display(),
+                status
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn copy_text(text: &str) -> Result<(), Error> {
+        for command in CLIPBOARD {
+            let Ok(mut child) = Command::new(command[0])
+                .args(&command[1..])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+            else {
+                continue;
+            };
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(text.as_bytes())?;
+            }
+            let status = child.wait()?;
+            if status.success() {
+                return Ok(());
+            }
+        }
+
+        Err(Error::Unsupported(
+            "未找到 wl-copy、xclip 或 xsel，无法复制到剪贴板".to_string(),
+        ))
+    }
+}
@@ -0,0 +1,167 @@
+use super::ImageMeta;
+use super::ImageSource;
+use crate::Downloader;
+use crate::Error;
+use crate::bing::parse_host;
+use futures_util::future::BoxFuture;
+use reqwest::Url;
+use reqwest::header::HeaderMap;
+use reqwest::header::HeaderValue;
+use serde::Deserialize;
+use std::collections::HashSet;
+use time::Date;
+use time::Duration;
+use time::OffsetDateTime;
+use time::UtcOffset;
+use time::format_description::BorrowedFormatItem;
+use time::macros::format_description;
+use time::macros::offset;
+
+pub const DEFAULT_HOST: &str = "https://api.nasa.gov";
+pub const DEMO_KEY: &str = "DEMO_KEY";
+
+/// APOD 按美国东部时间每天零点发布，不考虑夏令时
+const APOD_OFFSET: UtcOffset = offset!(-5);
+const API_DATE_FORMAT: &[BorrowedFormatItem<'_>] = format_description!("[year]-[month]-[day]");
+const PAGE_FORMAT: &[BorrowedFormatItem<'_>] =
+    format_description!("[year repr:last_two][month][day]");
+/// 视频日较多时最多往前查找的天数
+const LATEST_DAYS: i64 = 7;
+
+#[derive(Debug, Clone, Deserialize)]
+struct ApodImage {
+    date: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    copyright: Option<String>,
+    #[serde(default)]
+    media_type: String,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    hdurl: Option<String>,
+}
+
+/// NASA 每日天文图 (Astronomy Picture of the Day)
+pub struct ApodSource {
+    host: Url,
+    api_key: String,
+}
+
+impl ApodSource {
+    pub const NAME: &str = "apod";
+
+    pub fn new(host: Option<&str>, api_key: Option<&str>) -> Result<Self, Error> {
+        Ok(Self {
+            host: parse_host(host.unwrap_or(DEFAULT_HOST))?,
+            api_key: api_key.unwrap_or(DEMO_KEY).to_string(),
+        })
+    }
+
+    fn today() -> Date {
+        OffsetDateTime::now_utc().to_offset(APOD_OFFSET).date()
+    }
+
+    /// 使用请求头传递密钥，避免出现在日志的地址中
+    fn headers(&self) -> Result<HeaderMap, Error> {
+        let mut headers = HeaderMap::new();
+        let api_key = HeaderValue::from_str(&self.api_key)
+            .map_err(|_| Error::Config("source.apod.api_key 含有无效字符".to_string()))?;
+        headers.insert("X-Api-Key", api_key);
+        Ok(headers)
+    }
+
+    fn url(&self, start: Date, end: Date) -> Result<Url, Error> {
+        let mut url = self.host.join("/planetary/apod")?;
+        url.query_pairs_mut()
+            .append_pair(
+                "start_date",
+                &start.format(API_DATE_FORMAT).unwrap_or_default(),
+            )
+            .append_pair("end_date", &end.format(API_DATE_FORMAT).unwrap_or_default())
+            .append_pair("thumbs", "false");
+        Ok(url)
+    }
+
+    /// `days` 天内的图片，由新到旧，跳过视频
+    async fn images(&self, http: &Downloader, days: i64) -> Result<Vec<ImageMeta>, Error> {
+        let end = Self::today();
+        let start = end - Duration::days(days.max(1) - 1);
+        let mut images = http
+            .get_json::<Vec<ApodImage>>(self.url(start, end)?.as_str(), &self.headers()?)
+            .await?
+            .into_iter()
+            .filter_map(meta)
+            .collect::<Vec<_>>();
+        images.sort_by(|a, b| b.date.cmp(&a.date));
+
+        Ok(images)
+    }
+}
+
+fn meta(image: ApodImage) -> Option<ImageMeta> {
+    if image.media_type != "image" {
+        return None;
+    }
+    let date = Date::parse(&image.date, API_DATE_FORMAT).ok()?;
+    let mut urls = Vec::new();
+    urls.extend(image.hdurl);
+    urls.extend(image.url);
+    urls.dedup();
+    if urls.is_empty() {
+        return None;
+    }
+
+    let mut meta = ImageMeta::new(ApodSource::NAME, image.date, date);
+    meta.title = image.title;
+    meta.copyright = image
+        .copyright
+        .map(|copyright| copyright.split_whitespace().collect::<Vec<_>>().join(" "))
+        .unwrap_or_default();
+    meta.link = date
+        .format(PAGE_FORMAT)
+        .map(|page| format!("https://apod.nasa.gov/apod/ap{}.html", page))
+        .unwrap_or_default();
+    meta.urls = urls;
+    Some(meta)
+}
+
+impl ImageSource for ApodSource {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn key(&self) -> String {
+        format!("{}:{}", Self::NAME, self.host)
+    }
+
+    fn latest<'a>(&'a self, http: &'a Downloader) -> BoxFuture<'a, Result<ImageMeta, Error>> {
+        Box::pin(async move {
+            self.images(http, LATEST_DAYS)
+                .await?
+                .into_iter()
+                .next()
+                .ok_or_else(|| Error::EmptyFeed(format!("最近 {} 天没有 APOD 图片", LATEST_DAYS)))
+        })
+    }
+
+    fn history<'a>(
+        &'a self,
+        http: &'a Downloader,
+        n: usize,
+    ) -> BoxFuture<'a, Result<Vec<ImageMeta>, Error>> {
+        Box::pin(async move {
+            let mut seen = HashSet::new();
+            let mut images = self.images(http, n as i64 + LATEST_DAYS).await?;
+            images.retain(|image| seen.insert(image.id.clone()));
+            images.truncate(n);
+            Ok(images)
+        })
+    }
+
+    fn next_rollover(&self, _meta: &ImageMeta) -> Option<OffsetDateTime> {
+        let tomorrow = Self::today().next_day()?;
+        Some(tomorrow.midnight().assume_offset(APOD_OFFSET))
+    }
+}
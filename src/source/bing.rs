@@ -0,0 +1,197 @@
+use super::ImageMeta;
+use super::ImageSource;
+use crate::Downloader;
+use crate::Error;
+use crate::HpImage;
+use crate::HpJson;
+use crate::HpRequest;
+use crate::Market;
+use crate::Resolution;
+use crate::Validators;
+use futures_util::future::BoxFuture;
+use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
+use std::collections::HashSet;
+use time::Duration;
+use time::OffsetDateTime;
+use tracing::warn;
+
+const HISTORY_PAGES: [u8; 2] = [0, HpRequest::MAX_IDX];
+
+pub struct BingSource {
+    request: HpRequest,
+    resolution: Option<Resolution>,
+}
+
+impl BingSource {
+    pub const NAME: &str = "bing";
+
+    pub fn new(request: HpRequest, resolution: Option<Resolution>) -> Self {
+        Self {
+            request,
+            resolution,
+        }
+    }
+
+    pub fn request(&self) -> &HpRequest {
+        &self.request
+    }
+
+    /// 按偏好的分辨率由大到小排列，最后为接口返回的默认地址
+    pub fn image_urls(&self, image: &HpImage) -> Result<Vec<String>, Error> {
+        let mut image_urls = Vec::new();
+        if let Some(resolution) = self.resolution
+            && !image.urlbase.is_empty()
+        {
+            image_urls.extend(
+                resolution
+                    .ranked()
+                    .iter()
+                    .map(|r| r.image_url(&image.urlbase)),
+            );
+        }
+        if !image_urls.contains(&image.url) {
+            image_urls.push(image.url.clone());
+        }
+
+        image_urls
+            .iter()
+            .map(|image_url| Ok(self.request.image_url(image_url)?.to_string()))
+            .collect()
+    }
+
+    fn meta(&self, image: HpImage, request: &HpRequest) -> Result<ImageMeta, Error> {
+        Ok(ImageMeta {
+            source: Self::NAME.to_string(),
+            id: image.hsh.clone(),
+            date: image.startdate.clone(),
+            title: image.title.clone(),
+            copyright: image.copyright.clone(),
+            link: image.copyrightlink.clone(),
+            urls: self.image_urls(&image)?,
+            market: request.market().to_string(),
+            bing: Some(image),
+        })
+    }
+
+    async fn images(
+        &self,
+        http: &Downloader,
+        request: &HpRequest,
+        cached: Option<&Validators>,
+    ) -> Result<Option<(Vec<ImageMeta>, Validators)>, Error> {
+        let Some((hp_json, validators)) = http
+            .get_json_if_modified::<HpJson>(request.url().as_str(), &HeaderMap::new(), cached)
+            .await?
+        else {
+            return Ok(None);
+        };
+        let images = hp_json
+            .images
+            .into_iter()
+            .map(|image| self.meta(image, request))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Some((images, validators)))
+    }
+
+    /// 同一地址其他市场的必应壁纸，用于补全多个市场
+    pub fn with_market(&self, market: Market) -> Result<Self, Error> {
+        Ok(Self {
+            request: self.request.page(market, 0, 1)?,
+            resolution: self.resolution,
+        })
+    }
+}
+
+impl ImageSource for BingSource {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn key(&self) -> String {
+        self.request.url().to_string()
+    }
+
+    fn latest<'a>(&'a self, http: &'a Downloader) -> BoxFuture<'a, Result<ImageMeta, Error>> {
+        Box::pin(async move {
+            let (meta, _) =
+                self.latest_if_modified(http, None)
+                    .await?
+                    .ok_or_else(|| Error::HttpStatus {
+                        url: self.request.url().to_string(),
+                        status: StatusCode::NOT_MODIFIED,
+                    })?;
+            Ok(meta)
+        })
+    }
+
+    fn latest_if_modified<'a>(
+        &'a self,
+        http: &'a Downloader,
+        cached: Option<&'a Validators>,
+    ) -> BoxFuture<'a, Result<Option<(ImageMeta, Validators)>, Error>> {
+        Box::pin(async move {
+            let Some((images, validators)) = self.images(http, &self.request, cached).await? else {
+                return Ok(None);
+            };
+            let image = images
+                .into_iter()
+                .next()
+                .ok_or_else(|| Error::EmptyFeed("必应接口没有返回图片".to_string()))?;
+
+            Ok(Some((image, validators)))
+        })
+    }
+
+    /// 接口最多返回最近约 15 天的壁纸，分两页获取
+    fn history<'a>(
+        &'a self,
+        http: &'a Downloader,
+        n: usize,
+    ) -> BoxFuture<'a, Result<Vec<ImageMeta>, Error>> {
+        Box::pin(async move {
+            let mut history = Vec::new();
+            let mut seen = HashSet::new();
+            let mut last_error = None;
+            for idx in HISTORY_PAGES {
+                if history.len() >= n {
+                    break;
+                }
+
+                let request = self
+                    .request
+                    .page(self.request.market(), idx, HpRequest::MAX_N)?;
+                let images = match self.images(http, &request, None).await {
+                    Ok(Some((images, _))) => images,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        warn!("获取壁纸列表失败 {}: {:?}", request.url(), e);
+                        last_error = Some(e);
+                        continue;
+                    }
+                };
+                history.extend(images.into_iter().filter(|image| {
+                    seen.insert(image.key().unwrap_or_else(|| image.url().to_string()))
+                }));
+            }
+
+            if history.is_empty()
+                && let Some(e) = last_error
+            {
+                return Err(e);
+            }
+            history.truncate(n);
+
+            Ok(history)
+        })
+    }
+
+    /// `idx` 大于 0 时顺延相应天数
+    fn next_rollover(&self, meta: &ImageMeta) -> Option<OffsetDateTime> {
+        meta.bing
+            .as_ref()?
+            .next_rollover(self.request.market())
+            .map(|rollover| rollover + Duration::days(self.request.idx().into()))
+    }
+}
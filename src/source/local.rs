@@ -0,0 +1,470 @@
+use super::ImageMeta;
+use super::ImageSource;
+use crate::Downloader;
+use crate::Error;
+use crate::StateStore;
+use crate::json_file::read_json;
+use crate::json_file::write_json_atomic;
+use futures_util::future::BoxFuture;
+use globset::GlobBuilder;
+use globset::GlobSet;
+use globset::GlobSetBuilder;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::Duration;
+use std::time::SystemTime;
+use time::OffsetDateTime;
+use tracing::error;
+use tracing::info;
+use walkdir::WalkDir;
+
+const EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "bmp", "webp"];
+const SLIDESHOW_FILE: &str = "slideshow.json";
+
+/// 轮换顺序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SlideshowOrder {
+    /// 按相对路径依次轮换
+    #[default]
+    Sequential,
+    /// 随机打乱，全部显示过一遍之前不重复
+    Shuffle,
+    /// 随机选择，越新的图片（修改时间）越容易被选中
+    Recent,
+}
+
+impl SlideshowOrder {
+    pub const NAMES: &[&str] = &["sequential", "shuffle", "recent"];
+}
+
+impl FromStr for SlideshowOrder {
+    type Err = Error;
+
+    fn from_str(order: &str) -> Result<Self, Self::Err> {
+        match order.to_ascii_lowercase().as_str() {
+            "sequential" => Ok(Self::Sequential),
+            "shuffle" => Ok(Self::Shuffle),
+            "recent" => Ok(Self::Recent),
+            _ => Err(Error::Config(format!(
+                "未知的轮换顺序: {}，可选 {}",
+                order,
+                Self::NAMES.join("、")
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for SlideshowOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Sequential => "sequential",
+            Self::Shuffle => "shuffle",
+            Self::Recent => "recent",
+        })
+    }
+}
+
+/// 不含 `/` 的模式匹配文件名，否则匹配相对于目录的路径，不区分大小写
+#[derive(Debug, Clone)]
+struct Filter {
+    patterns: Vec<String>,
+    names: GlobSet,
+    paths: GlobSet,
+}
+
+impl Filter {
+    fn new(patterns: &[String]) -> Result<Self, Error> {
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        for pattern in patterns {
+            let glob = GlobBuilder::new(pattern)
+                .case_insensitive(true)
+                .literal_separator(true)
+                .build()
+                .map_err(|e| Error::Config(format!("无效的匹配模式 {}: {}", pattern, e)))?;
+            if pattern.contains('/') {
+                paths.add(glob);
+            } else {
+                names.add(glob);
+            }
+        }
+
+        Ok(Self {
+            patterns: patterns.to_vec(),
+            names: names.build().map_err(|e| Error::Config(e.to_string()))?,
+            paths: paths.build().map_err(|e| Error::Config(e.to_string()))?,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    fn is_match(&self, relative: &Path) -> bool {
+        relative
+            .file_name()
+            .is_some_and(|name| self.names.is_match(name))
+            || self.paths.is_match(relative)
+    }
+}
+
+/// 持久化的轮换进度，目录或过滤条件变化后重新开始，仍避开上一张
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct Slideshow {
+    key: String,
+    last: Option<PathBuf>,
+    /// 随机轮换中尚未显示的图片
+    remaining: Vec<PathBuf>,
+}
+
+struct LocalFile {
+    path: PathBuf,
+    relative: PathBuf,
+    modified: SystemTime,
+}
+
+/// 本地目录中的图片，按设置的顺序轮换，不需要网络
+pub struct LocalSource {
+    dir: PathBuf,
+    recursive: bool,
+    include: Filter,
+    exclude: Filter,
+    order: SlideshowOrder,
+    interval: Option<Duration>,
+    state_path: Option<PathBuf>,
+    slideshow: Arc<Mutex<Slideshow>>,
+}
+
+impl LocalSource {
+    pub const NAME: &str = "local";
+
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            recursive: false,
+            include: Filter::new(&[]).expect("empty filter is valid"),
+            exclude: Filter::new(&[]).expect("empty filter is valid"),
+            order: SlideshowOrder::default(),
+            interval: None,
+            state_path: None,
+            slideshow: Arc::new(Mutex::new(Slideshow::default())),
+        }
+    }
+
+    pub fn default_state_path() -> Option<PathBuf> {
+        StateStore::dir().map(|dir| dir.join(SLIDESHOW_FILE))
+    }
+
+    pub fn with_recursive(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// 例如 `*.jpg`、`favorites/**`，`include` 为空时包含所有图片
+    pub fn with_filters(mut self, include: &[String], exclude: &[String]) -> Result<Self, Error> {
+        self.include = Filter::new(include)?;
+        self.exclude = Filter::new(exclude)?;
+        Ok(self)
+    }
+
+    pub fn with_order(mut self, order: SlideshowOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// 保存轮换进度，重启后继续，文件不存在或无法解析时从头开始
+    pub fn with_state_path(mut self, path: PathBuf) -> Self {
+        let slideshow = match read_json::<Slideshow>(&path) {
+            Ok(slideshow) => slideshow.unwrap_or_default(),
+            Err(e) => {
+                error!("加载轮换进度失败: {:?}", e);
+                Slideshow::default()
+            }
+        };
+        self.slideshow = Arc::new(Mutex::new(slideshow));
+        self.state_path = Some(path);
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn order(&self) -> SlideshowOrder {
+        self.order
+    }
+
+    /// 按相对路径排序，遍历目录在阻塞线程中进行
+    async fn files(&self) -> Result<Vec<LocalFile>, Error> {
+        let dir = self.dir.clone();
+        let recursive = self.recursive;
+        let (include, exclude) = (self.include.clone(), self.exclude.clone());
+        tokio::task::spawn_blocking(move || scan(&dir, recursive, &include, &exclude))
+            .await
+            .map_err(|e| Error::Platform(format!("遍历目录的任务异常结束: {}", e)))?
+    }
+
+    /// 选出下一张图片，只有应用后才会记录进度
+    async fn next_file(&self) -> Result<ImageMeta, Error> {
+        let files = self.files().await?;
+        if files.is_empty() {
+            return Err(Error::EmptyFeed(format!(
+                "目录中没有图片: {}",
+                self.dir.display()
+            )));
+        }
+
+        let mut slideshow = self
+            .slideshow
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let key = self.key();
+        if slideshow.key != key {
+            *slideshow = Slideshow {
+                key,
+                last: slideshow.last.take(),
+                remaining: Vec::new(),
+            };
+        }
+
+        let file = match self.order {
+            SlideshowOrder::Sequential => {
+                let next = slideshow
+                    .last
+                    .as_ref()
+                    .and_then(|last| files.iter().position(|file| &file.path == last))
+                    .map_or(0, |index| (index + 1) % files.len());
+                &files[next]
+            }
+            SlideshowOrder::Shuffle => {
+                let paths = files
+                    .iter()
+                    .map(|file| file.path.as_path())
+                    .collect::<HashSet<_>>();
+                slideshow
+                    .remaining
+                    .retain(|path| paths.contains(path.as_path()));
+                if slideshow.remaining.is_empty() {
+                    slideshow.remaining = shuffled(&files, slideshow.last.as_deref());
+                    self.save(&slideshow);
+                }
+                let next = &slideshow.remaining[0];
+                files
+                    .iter()
+                    .find(|file| &file.path == next)
+                    .unwrap_or(&files[0])
+            }
+            SlideshowOrder::Recent => weighted_by_recency(&files, slideshow.last.as_deref()),
+        };
+
+        Ok(meta(file))
+    }
+
+    fn save(&self, slideshow: &Slideshow) {
+        if let Some(path) = &self.state_path
+            && let Err(e) = write_json_atomic(path, slideshow)
+        {
+            error!("保存轮换进度失败: {:?}", e);
+        }
+    }
+}
+
+/// 按扩展名与过滤条件列出目录中的图片
+fn scan(
+    dir: &Path,
+    recursive: bool,
+    include: &Filter,
+    exclude: &Filter,
+) -> Result<Vec<LocalFile>, Error> {
+    if !dir.is_dir() {
+        return Err(Error::io(dir)(io::ErrorKind::NotFound.into()));
+    }
+
+    let mut walk = WalkDir::new(dir).follow_links(true).sort_by_file_name();
+    if !recursive {
+        walk = walk.max_depth(1);
+    }
+
+    let mut files = Vec::new();
+    for entry in walk {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                info!("跳过无法读取的路径: {}", e);
+                continue;
+            }
+        };
+        if !entry.file_type().is_file() || !is_image(entry.path()) {
+            continue;
+        }
+        let relative = entry
+            .path()
+            .strip_prefix(dir)
+            .unwrap_or(entry.path())
+            .to_path_buf();
+        if (!include.is_empty() && !include.is_match(&relative)) || exclude.is_match(&relative) {
+            continue;
+        }
+        let modified = entry
+            .metadata()
+            .ok()
+            .and_then(|metadata| metadata.modified().ok())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        files.push(LocalFile {
+            path: entry.into_path(),
+            relative,
+            modified,
+        });
+    }
+
+    Ok(files)
+}
+
+/// 随机排列所有图片，避免与上一张重复
+fn shuffled(files: &[LocalFile], last: Option<&Path>) -> Vec<PathBuf> {
+    let mut paths = files
+        .iter()
+        .map(|file| file.path.clone())
+        .collect::<Vec<_>>();
+    fastrand::shuffle(&mut paths);
+    if paths.len() > 1 && Some(paths[0].as_path()) == last {
+        let other = fastrand::usize(1..paths.len());
+        paths.swap(0, other);
+    }
+    paths
+}
+
+/// 按修改时间由新到旧排名，权重依次为 n、n-1、...、1，不会连续选中同一张
+fn weighted_by_recency<'a>(files: &'a [LocalFile], last: Option<&Path>) -> &'a LocalFile {
+    let mut candidates = files
+        .iter()
+        .filter(|file| files.len() == 1 || Some(file.path.as_path()) != last)
+        .collect::<Vec<_>>();
+    candidates.sort_by(|a, b| b.modified.cmp(&a.modified));
+
+    let n = candidates.len() as u64;
+    let mut pick = fastrand::u64(0..n * (n + 1) / 2);
+    for (rank, file) in candidates.iter().enumerate() {
+        let weight = n - rank as u64;
+        if pick < weight {
+            return file;
+        }
+        pick -= weight;
+    }
+    candidates[0]
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            EXTENSIONS
+                .iter()
+                .any(|image| image.eq_ignore_ascii_case(extension))
+        })
+}
+
+fn meta(file: &LocalFile) -> ImageMeta {
+    let path = file.path.to_string_lossy().into_owned();
+    let mut meta = ImageMeta::new(
+        LocalSource::NAME,
+        path.clone(),
+        OffsetDateTime::from(file.modified).date(),
+    );
+    meta.title = file
+        .relative
+        .with_extension("")
+        .to_string_lossy()
+        .into_owned();
+    meta.urls = vec![path];
+    meta
+}
+
+impl ImageSource for LocalSource {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn key(&self) -> String {
+        format!(
+            "{}:{}:{}:{}:{}:{}",
+            Self::NAME,
+            self.dir.display(),
+            self.recursive,
+            self.include.patterns.join(","),
+            self.exclude.patterns.join(","),
+            self.order
+        )
+    }
+
+    fn latest<'a>(&'a self, _http: &'a Downloader) -> BoxFuture<'a, Result<ImageMeta, Error>> {
+        Box::pin(self.next_file())
+    }
+
+    /// 按修改时间由新到旧
+    fn history<'a>(
+        &'a self,
+        _http: &'a Downloader,
+        n: usize,
+    ) -> BoxFuture<'a, Result<Vec<ImageMeta>, Error>> {
+        Box::pin(async move {
+            let mut files = self.files().await?;
+            files.sort_by(|a, b| b.modified.cmp(&a.modified));
+            Ok(files.iter().take(n).map(meta).collect())
+        })
+    }
+
+    /// 直接使用原文件，不复制到壁纸库
+    fn download<'a>(
+        &'a self,
+        _http: &'a Downloader,
+        meta: &'a ImageMeta,
+        _to_dir: &'a Path,
+    ) -> BoxFuture<'a, Result<PathBuf, Error>> {
+        Box::pin(async move {
+            let path = PathBuf::from(meta.url());
+            if !path.is_file() {
+                return Err(Error::io(&path)(io::ErrorKind::NotFound.into()));
+            }
+            Ok(path)
+        })
+    }
+
+    fn applied(&self, meta: &ImageMeta) -> Result<(), Error> {
+        let path = PathBuf::from(meta.url());
+        let mut slideshow = self
+            .slideshow
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        slideshow.key = self.key();
+        slideshow.remaining.retain(|remaining| remaining != &path);
+        slideshow.last = Some(path);
+        match &self.state_path {
+            Some(state_path) => write_json_atomic(state_path, &*slideshow),
+            None => Ok(()),
+        }
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        self.interval
+    }
+
+    fn archives(&self) -> bool {
+        false
+    }
+}
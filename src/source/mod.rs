@@ -0,0 +1,157 @@
+mod apod;
+mod bing;
+mod local;
+mod unsplash;
+mod wikimedia;
+
+pub use apod::ApodSource;
+pub use bing::BingSource;
+pub use local::LocalSource;
+pub use local::SlideshowOrder;
+pub use unsplash::UnsplashSource;
+pub use wikimedia::WikimediaSource;
+
+use crate::Downloader;
+use crate::Error;
+use crate::HpImage;
+use crate::Validators;
+use futures_util::future::BoxFuture;
+use serde::Deserialize;
+use serde::Serialize;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use time::Date;
+use time::OffsetDateTime;
+use time::format_description::BorrowedFormatItem;
+use time::macros::format_description;
+
+const DATE_FORMAT: &[BorrowedFormatItem<'_>] = format_description!("[year][month][day]");
+
+/// 与来源无关的壁纸信息，字段别名兼容旧版本保存的必应 `HpImage`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageMeta {
+    #[serde(default = "default_source")]
+    pub source: String,
+    /// 来源内唯一的标识，例如必应的 `hsh`
+    #[serde(alias = "hsh")]
+    pub id: String,
+    /// 例如 `20261018`
+    #[serde(alias = "startdate")]
+    pub date: String,
+    pub title: String,
+    pub copyright: String,
+    #[serde(alias = "copyrightlink")]
+    pub link: String,
+    /// 按偏好排列的图片地址，依次尝试
+    pub urls: Vec<String>,
+    pub market: String,
+    pub bing: Option<HpImage>,
+}
+
+fn default_source() -> String {
+    BingSource::NAME.to_string()
+}
+
+impl ImageMeta {
+    pub fn new(source: &str, id: impl Into<String>, date: Date) -> Self {
+        Self {
+            source: source.to_string(),
+            id: id.into(),
+            date: date.format(DATE_FORMAT).unwrap_or_default(),
+            ..Self::default()
+        }
+    }
+
+    pub fn date(&self) -> Option<Date> {
+        Date::parse(&self.date, DATE_FORMAT).ok()
+    }
+
+    /// 首选的图片地址
+    pub fn url(&self) -> &str {
+        self.urls.first().map(String::as_str).unwrap_or_default()
+    }
+
+    /// 用于文件名与壁纸库索引，必应为市场代码，其他来源为来源名称
+    pub fn label(&self) -> &str {
+        if self.market.is_empty() {
+            &self.source
+        } else {
+            &self.market
+        }
+    }
+
+    /// 去重用的键，缺少 `id` 时返回 `None`
+    pub fn key(&self) -> Option<String> {
+        (!self.id.is_empty()).then(|| format!("{}:{}", self.source, self.id))
+    }
+
+    /// 优先按 `id` 判断，缺失时退回比较首选地址
+    pub fn same_image(&self, other: &ImageMeta) -> bool {
+        if self.source != other.source {
+            return false;
+        }
+        if !self.id.is_empty() && !other.id.is_empty() {
+            return self.id == other.id;
+        }
+
+        self.url() == other.url()
+    }
+}
+
+/// 壁纸来源，网络请求统一通过 `Downloader` 发送以共享重试、超时与缓存
+pub trait ImageSource: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// 来源的请求标识，用作条件请求的缓存键，变化时立即更新
+    fn key(&self) -> String;
+
+    fn latest<'a>(&'a self, http: &'a Downloader) -> BoxFuture<'a, Result<ImageMeta, Error>>;
+
+    /// 支持条件请求的来源在未变化 (304) 时返回 `None`
+    fn latest_if_modified<'a>(
+        &'a self,
+        http: &'a Downloader,
+        _cached: Option<&'a Validators>,
+    ) -> BoxFuture<'a, Result<Option<(ImageMeta, Validators)>, Error>> {
+        Box::pin(async move { Ok(Some((self.latest(http).await?, Validators::default()))) })
+    }
+
+    /// 最近的 `n` 张壁纸，由新到旧
+    fn history<'a>(
+        &'a self,
+        http: &'a Downloader,
+        n: usize,
+    ) -> BoxFuture<'a, Result<Vec<ImageMeta>, Error>>;
+
+    /// 下载到 `to_dir`，返回图片路径
+    fn download<'a>(
+        &'a self,
+        http: &'a Downloader,
+        meta: &'a ImageMeta,
+        to_dir: &'a Path,
+    ) -> BoxFuture<'a, Result<PathBuf, Error>> {
+        Box::pin(http.download_first(meta, to_dir))
+    }
+
+    /// 下一张壁纸的预计发布时间，无法预计时按固定间隔更新
+    fn next_rollover(&self, _meta: &ImageMeta) -> Option<OffsetDateTime> {
+        None
+    }
+
+    /// 图片成为当前壁纸后调用（包括已是当前壁纸而跳过更新时），用于记录轮换进度
+    fn applied(&self, _meta: &ImageMeta) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// 来源自己的更新间隔，设置后代替 `[update]` 中的计划
+    fn interval(&self) -> Option<Duration> {
+        None
+    }
+
+    /// 是否将图片保存到壁纸库
+    fn archives(&self) -> bool {
+        true
+    }
+}
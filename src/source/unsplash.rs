@@ -0,0 +1,209 @@
+use super::ImageMeta;
+use super::ImageSource;
+use crate::Downloader;
+use crate::Error;
+use crate::Resolution;
+use crate::bing::parse_host;
+use futures_util::future::BoxFuture;
+use reqwest::Url;
+use reqwest::header::AUTHORIZATION;
+use reqwest::header::HeaderMap;
+use reqwest::header::HeaderValue;
+use serde::Deserialize;
+use std::path::Path;
+use std::path::PathBuf;
+use time::OffsetDateTime;
+use tracing::warn;
+
+pub const DEFAULT_HOST: &str = "https://api.unsplash.com";
+
+/// 单次请求最多返回的图片数
+const MAX_COUNT: usize = 30;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct Photo {
+    id: String,
+    description: Option<String>,
+    alt_description: Option<String>,
+    urls: PhotoUrls,
+    links: PhotoLinks,
+    user: User,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct PhotoUrls {
+    raw: String,
+    full: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct PhotoLinks {
+    html: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct User {
+    name: String,
+}
+
+/// Unsplash 随机图片，需要在 Unsplash 开发者后台申请 Access Key
+pub struct UnsplashSource {
+    host: Url,
+    access_key: String,
+    query: Option<String>,
+    resolution: Option<Resolution>,
+}
+
+impl UnsplashSource {
+    pub const NAME: &str = "unsplash";
+
+    pub fn new(
+        host: Option<&str>,
+        access_key: &str,
+        query: Option<&str>,
+        resolution: Option<Resolution>,
+    ) -> Result<Self, Error> {
+        if access_key.is_empty() {
+            return Err(Error::Config(
+                "Unsplash 需要设置 source.unsplash.access_key".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            host: parse_host(host.unwrap_or(DEFAULT_HOST))?,
+            access_key: access_key.to_string(),
+            query: query.filter(|query| !query.is_empty()).map(str::to_string),
+            resolution,
+        })
+    }
+
+    fn headers(&self) -> Result<HeaderMap, Error> {
+        let mut headers = HeaderMap::new();
+        let authorization = HeaderValue::from_str(&format!("Client-ID {}", self.access_key))
+            .map_err(|_| Error::Config("source.unsplash.access_key 含有无效字符".to_string()))?;
+        headers.insert(AUTHORIZATION, authorization);
+        headers.insert("Accept-Version", HeaderValue::from_static("v1"));
+        Ok(headers)
+    }
+
+    async fn photos(&self, http: &Downloader, count: usize) -> Result<Vec<ImageMeta>, Error> {
+        let mut url = self.host.join("/photos/random")?;
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("count", &count.clamp(1, MAX_COUNT).to_string());
+            let orientation = match self.resolution {
+                Some(resolution) if resolution.is_portrait() => "portrait",
+                _ => "landscape",
+            };
+            query.append_pair("orientation", orientation);
+            if let Some(search) = &self.query {
+                query.append_pair("query", search);
+            }
+        }
+
+        let photos = http
+            .get_json::<Vec<Photo>>(url.as_str(), &self.headers()?)
+            .await?;
+
+        Ok(photos
+            .into_iter()
+            .filter_map(|photo| self.meta(photo))
+            .collect())
+    }
+
+    fn meta(&self, photo: Photo) -> Option<ImageMeta> {
+        if photo.id.is_empty() || photo.urls.raw.is_empty() {
+            return None;
+        }
+
+        let mut urls = Vec::new();
+        if let Some(resolution) = self.resolution {
+            let separator = if photo.urls.raw.contains('?') {
+                '&'
+            } else {
+                '?'
+            };
+            urls.push(format!(
+                "{}{}w={}&h={}&fit=crop&fm=jpg&q=85",
+                photo.urls.raw,
+                separator,
+                resolution.width(),
+                resolution.height()
+            ));
+        }
+        if !photo.urls.full.is_empty() {
+            urls.push(photo.urls.full);
+        }
+
+        let today = OffsetDateTime::now_utc().date();
+        let mut meta = ImageMeta::new(Self::NAME, photo.id, today);
+        meta.title = photo
+            .description
+            .or(photo.alt_description)
+            .unwrap_or_default();
+        if !photo.user.name.is_empty() {
+            meta.copyright = format!("Photo by {} on Unsplash", photo.user.name);
+        }
+        meta.link = photo.links.html;
+        meta.urls = urls;
+        Some(meta)
+    }
+
+    /// Unsplash 要求在下载图片时通知下载统计接口
+    async fn track_download(&self, http: &Downloader, id: &str) -> Result<(), Error> {
+        let url = self.host.join(&format!("/photos/{}/download", id))?;
+        http.ping(url.as_str(), &self.headers()?).await
+    }
+}
+
+impl ImageSource for UnsplashSource {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn key(&self) -> String {
+        format!(
+            "{}:{}:{}",
+            Self::NAME,
+            self.host,
+            self.query.as_deref().unwrap_or_default()
+        )
+    }
+
+    fn latest<'a>(&'a self, http: &'a Downloader) -> BoxFuture<'a, Result<ImageMeta, Error>> {
+        Box::pin(async move {
+            self.photos(http, 1)
+                .await?
+                .into_iter()
+                .next()
+                .ok_or_else(|| Error::EmptyFeed("Unsplash 没有返回图片".to_string()))
+        })
+    }
+
+    fn history<'a>(
+        &'a self,
+        http: &'a Downloader,
+        n: usize,
+    ) -> BoxFuture<'a, Result<Vec<ImageMeta>, Error>> {
+        Box::pin(self.photos(http, n))
+    }
+
+    fn download<'a>(
+        &'a self,
+        http: &'a Downloader,
+        meta: &'a ImageMeta,
+        to_dir: &'a Path,
+    ) -> BoxFuture<'a, Result<PathBuf, Error>> {
+        Box::pin(async move {
+            let path = http.download_first(meta, to_dir).await?;
+            if let Err(e) = self.track_download(http, &meta.id).await {
+                warn!("通知 Unsplash 下载失败: {:?}", e);
+            }
+            Ok(path)
+        })
+    }
+}
@@ -0,0 +1,211 @@
+use super::ImageMeta;
+use super::ImageSource;
+use crate::Downloader;
+use crate::Error;
+use crate::Resolution;
+use crate::bing::parse_host;
+use futures_util::future::BoxFuture;
+use reqwest::Url;
+use reqwest::header::HeaderMap;
+use serde::Deserialize;
+use time::Date;
+use time::Duration;
+use time::OffsetDateTime;
+use tracing::warn;
+
+pub const DEFAULT_HOST: &str = "https://api.wikimedia.org";
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// 当天尚无每日图片时往前查找的天数
+const LATEST_DAYS: i64 = 2;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct FeaturedJson {
+    image: Option<FeaturedImage>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct FeaturedImage {
+    title: String,
+    thumbnail: Option<ImageFile>,
+    image: Option<ImageFile>,
+    file_page: String,
+    artist: Option<Text>,
+    license: Option<License>,
+    description: Option<Text>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct ImageFile {
+    source: String,
+    width: u32,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct Text {
+    text: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct License {
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+/// 维基共享资源每日图片 (Picture of the Day)
+pub struct WikimediaSource {
+    host: Url,
+    language: String,
+    resolution: Option<Resolution>,
+}
+
+impl WikimediaSource {
+    pub const NAME: &str = "wikimedia";
+
+    pub fn new(
+        host: Option<&str>,
+        language: Option<&str>,
+        resolution: Option<Resolution>,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            host: parse_host(host.unwrap_or(DEFAULT_HOST))?,
+            language: language.unwrap_or(DEFAULT_LANGUAGE).to_string(),
+            resolution,
+        })
+    }
+
+    fn today() -> Date {
+        OffsetDateTime::now_utc().date()
+    }
+
+    fn url(&self, date: Date) -> Result<Url, Error> {
+        Ok(self.host.join(&format!(
+            "/feed/v1/wikipedia/{}/featured/{:04}/{:02}/{:02}",
+            self.language,
+            date.year(),
+            u8::from(date.month()),
+            date.day()
+        ))?)
+    }
+
+    /// 当天没有每日图片时返回 `None`
+    async fn image(&self, http: &Downloader, date: Date) -> Result<Option<ImageMeta>, Error> {
+        let featured = http
+            .get_json::<FeaturedJson>(self.url(date)?.as_str(), &HeaderMap::new())
+            .await?;
+
+        Ok(featured.image.and_then(|image| self.meta(image, date)))
+    }
+
+    fn meta(&self, image: FeaturedImage, date: Date) -> Option<ImageMeta> {
+        let original = image.image.filter(|file| !file.source.is_empty())?;
+        let mut urls = Vec::new();
+        if let Some(resolution) = self.resolution
+            && let Some(thumbnail) = &image.thumbnail
+            && resolution.width() < original.width
+            && let Some(url) = resize_thumbnail(&thumbnail.source, resolution.width())
+        {
+            urls.push(url);
+        }
+        urls.push(original.source);
+
+        let title = image
+            .description
+            .map(|description| description.text)
+            .filter(|text| !text.is_empty())
+            .unwrap_or_else(|| {
+                image
+                    .title
+                    .trim_start_matches("File:")
+                    .rsplit_once('.')
+                    .map_or(image.title.as_str(), |(name, _)| name)
+                    .to_string()
+            });
+        let copyright = [
+            image.artist.map(|artist| artist.text),
+            image.license.map(|license| license.kind),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(" / ");
+
+        let mut meta = ImageMeta::new(Self::NAME, image.title, date);
+        meta.title = title;
+        meta.copyright = copyright;
+        meta.link = image.file_page;
+        meta.urls = urls;
+        Some(meta)
+    }
+}
+
+/// 缩略图地址形如 `.../thumb/a/ab/Name.jpg/640px-Name.jpg`，替换其中的宽度
+fn resize_thumbnail(thumbnail: &str, width: u32) -> Option<String> {
+    let (dir, file) = thumbnail.rsplit_once('/')?;
+    let (_, name) = file.split_once("px-")?;
+    Some(format!("{}/{}px-{}", dir, width, name))
+}
+
+impl ImageSource for WikimediaSource {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn key(&self) -> String {
+        format!("{}:{}:{}", Self::NAME, self.host, self.language)
+    }
+
+    fn latest<'a>(&'a self, http: &'a Downloader) -> BoxFuture<'a, Result<ImageMeta, Error>> {
+        Box::pin(async move {
+            let today = Self::today();
+            for days in 0..LATEST_DAYS {
+                if let Some(image) = self.image(http, today - Duration::days(days)).await? {
+                    return Ok(image);
+                }
+            }
+
+            Err(Error::EmptyFeed("没有找到维基共享资源每日图片".to_string()))
+        })
+    }
+
+    fn history<'a>(
+        &'a self,
+        http: &'a Downloader,
+        n: usize,
+    ) -> BoxFuture<'a, Result<Vec<ImageMeta>, Error>> {
+        Box::pin(async move {
+            let today = Self::today();
+            let mut images = Vec::new();
+            let mut last_error = None;
+            for days in 0..n as i64 {
+                let date = today - Duration::days(days);
+                match self.image(http, date).await {
+                    Ok(Some(image)) => images.push(image),
+                    Ok(None) => {}
+                    Err(e) => {
+                        warn!("获取 {} 的每日图片失败: {:?}", date, e);
+                        last_error = Some(e);
+                    }
+                }
+            }
+
+            if images.is_empty()
+                && let Some(e) = last_error
+            {
+                return Err(e);
+            }
+
+            Ok(images)
+        })
+    }
+
+    fn next_rollover(&self, _meta: &ImageMeta) -> Option<OffsetDateTime> {
+        Some(Self::today().next_day()?.midnight().assume_utc())
+    }
+}
@@ -0,0 +1,89 @@
+use crate::Error;
+use crate::ImageMeta;
+use crate::json_file::read_json;
+use crate::json_file::write_json_atomic;
+use serde::Deserialize;
+use serde::Serialize;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tokio::sync::Mutex;
+use tracing::error;
+use tracing::info;
+
+const APP_DIR: &str = "bingwallpaper";
+const STATE_FILE: &str = "state.json";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    pub image: Option<ImageMeta>,
+    pub path: Option<PathBuf>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub updated_at: Option<OffsetDateTime>,
+}
+
+#[derive(Clone)]
+pub struct StateStore {
+    path: Option<PathBuf>,
+    state: Arc<Mutex<State>>,
+}
+
+impl StateStore {
+    pub fn dir() -> Option<PathBuf> {
+        dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .map(|dir| dir.join(APP_DIR))
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        Self::dir().map(|dir| dir.join(STATE_FILE))
+    }
+
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            state: Arc::new(Mutex::new(State::default())),
+        }
+    }
+
+    /// 状态文件不存在或无法解析时从空状态开始
+    pub fn open(path: PathBuf) -> Self {
+        let state = match read_json(&path) {
+            Ok(Some(state)) => {
+                info!("加载状态: {}", path.display());
+                state
+            }
+            Ok(None) => State::default(),
+            Err(e) => {
+                error!("加载状态失败: {:?}", e);
+                State::default()
+            }
+        };
+
+        Self {
+            path: Some(path),
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub async fn get(&self) -> State {
+        self.state.lock().await.clone()
+    }
+
+    pub async fn set(&self, state: State) -> Result<(), Error> {
+        let mut current = self.state.lock().await;
+        *current = state;
+
+        if let Some(path) = &self.path {
+            write_json_atomic(path, &*current)?;
+        }
+
+        Ok(())
+    }
+}
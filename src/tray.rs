@@ -0,0 +1,491 @@
+use crate::LOG_FILE_PREFIX;
+use crate::LOG_FILE_SUFFIX;
+use crate::cli::ConfigSource;
+use anyhow::Result;
+use bingwallpaper::Config;
+use bingwallpaper::ImageMeta;
+use bingwallpaper::WallpaperService;
+use bingwallpaper::shell;
+use image::GenericImageView;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::error;
+use tray_icon::Icon;
+use tray_icon::TrayIcon;
+use tray_icon::TrayIconBuilder;
+use tray_icon::TrayIconEvent;
+use tray_icon::menu::Menu;
+use tray_icon::menu::MenuEvent;
+use tray_icon::menu::MenuItem;
+use tray_icon::menu::PredefinedMenuItem;
+use winit::application::ApplicationHandler;
+use winit::event_loop::EventLoop;
+use winit::event_loop::EventLoopProxy;
+
+pub fn run(
+    service: WallpaperService,
+    source: ConfigSource,
+    log_dir: Option<PathBuf>,
+) -> Result<()> {
+    let event_loop = EventLoop::<UserEvent>::with_user_event().build()?;
+
+    let proxy = event_loop.create_proxy();
+    TrayIconEvent::set_event_handler(Some(move |event| {
+        if let Err(e) = proxy.send_event(UserEvent::TrayIconEvent(event)) {
+            error!("Event handle error: {:?}", e);
+        }
+    }));
+    let proxy = event_loop.create_proxy();
+    MenuEvent::set_event_handler(Some(move |event| {
+        if let Err(e) = proxy.send_event(UserEvent::MenuEvent(event)) {
+            error!("Event handle error: {:?}", e);
+        }
+    }));
+
+    let proxy = event_loop.create_proxy();
+
+    let mut app = Application::new(proxy, service, source, log_dir)?;
+
+    event_loop.run_app(&mut app)?;
+
+    Ok(())
+}
+
+const TOOLTIP: &str = "BingWallpaper";
+const TOOLTIP_MAX_CHARS: usize = 127;
+const HEADER_MAX_CHARS: usize = 80;
+
+#[derive(Debug)]
+enum UserEvent {
+    TrayIconEvent(tray_icon::TrayIconEvent),
+    MenuEvent(tray_icon::menu::MenuEvent),
+    /// 配置文件重新加载后更新托盘使用的服务与菜单文字
+    ConfigChanged,
+    /// 由后台任务发送，新壁纸应用后更新提示文字与菜单
+    WallpaperApplied {
+        image: Box<ImageMeta>,
+        path: PathBuf,
+    },
+}
+
+struct Application {
+    rt: Runtime,
+    tray_icon: TrayIcon,
+    tooltip: String,
+    menu_item_header: MenuItem,
+    menu_item_learn_more: MenuItem,
+    menu_item_daily_update: MenuItem,
+    menu_item_update: MenuItem,
+    menu_item_open_image: MenuItem,
+    menu_item_open_folder: MenuItem,
+    menu_item_open_log: MenuItem,
+    menu_item_copy_info: MenuItem,
+    menu_item_exit: MenuItem,
+    daily_updating: Option<JoinHandle<()>>,
+    user_event_proxy: EventLoopProxy<UserEvent>,
+    service: WallpaperService,
+    config: watch::Receiver<Arc<Config>>,
+    image: Option<ImageMeta>,
+    image_path: Option<PathBuf>,
+    /// 日志输出到标准错误时为 `None`
+    log_dir: Option<PathBuf>,
+}
+
+impl Application {
+    fn new(
+        proxy: EventLoopProxy<UserEvent>,
+        service: WallpaperService,
+        source: ConfigSource,
+        log_dir: Option<PathBuf>,
+    ) -> Result<Self> {
+        let rt = Runtime::new()?;
+        let (config, watching) = source.watch();
+        rt.spawn(watching);
+        rt.spawn({
+            let mut config = config.clone();
+            let proxy = proxy.clone();
+            async move {
+                while config.changed().await.is_ok() {
+                    if let Err(e) = proxy.send_event(UserEvent::ConfigChanged) {
+                        error!("Event handle error: {:?}", e);
+                        break;
+                    }
+                }
+            }
+        });
+        let service = service.with_on_applied({
+            let proxy = proxy.clone();
+            Arc::new(move |image, path| {
+                let event = UserEvent::WallpaperApplied {
+                    image: Box::new(image.clone()),
+                    path: path.to_path_buf(),
+                };
+                if let Err(e) = proxy.send_event(event) {
+                    error!("Event handle error: {:?}", e);
+                }
+            })
+        });
+        let menu = config.borrow().menu.clone();
+        let menu_item_header = MenuItem::new(TOOLTIP, false, None);
+        let menu_item_learn_more = MenuItem::new(&menu.learn_more, false, None);
+        let menu_item_daily_update = MenuItem::new(&menu.enable_daily_update, true, None);
+        let menu_item_update = MenuItem::new(&menu.update, true, None);
+        let menu_item_open_image = MenuItem::new(&menu.open_image, false, None);
+        let menu_item_open_folder = MenuItem::new(&menu.open_folder, true, None);
+        let menu_item_open_log = MenuItem::new(&menu.open_log, log_dir.is_some(), None);
+        let menu_item_copy_info = MenuItem::new(&menu.copy_info, false, None);
+        let menu_item_exit = MenuItem::new(&menu.exit, true, None);
+        let tray_menu = Self::new_tray_menu(&[
+            &[&menu_item_header, &menu_item_learn_more],
+            &[&menu_item_daily_update, &menu_item_update],
+            &[
+                &menu_item_open_image,
+                &menu_item_open_folder,
+                &menu_item_open_log,
+                &menu_item_copy_info,
+            ],
+            &[&menu_item_exit],
+        ])?;
+        let tray_icon = Self::new_tray_icon(tray_menu)?;
+
+        Ok(Self {
+            rt,
+            tray_icon,
+            tooltip: TOOLTIP.to_string(),
+            menu_item_header,
+            menu_item_learn_more,
+            menu_item_daily_update,
+            menu_item_update,
+            menu_item_open_image,
+            menu_item_open_folder,
+            menu_item_open_log,
+            menu_item_copy_info,
+            menu_item_exit,
+            daily_updating: None,
+            user_event_proxy: proxy,
+            service,
+            config,
+            image: None,
+            image_path: None,
+            log_dir,
+        })
+    }
+
+    fn new_tray_icon(tray_menu: Menu) -> Result<TrayIcon> {
+        let icon = Self::load_icon()?;
+
+        let tray_icon = TrayIconBuilder::new()
+            .with_menu(Box::new(tray_menu))
+            .with_tooltip(TOOLTIP)
+            .with_icon(icon)
+            .with_title("x")
+            .build()?;
+
+        Ok(tray_icon)
+    }
+
+    /// 各组之间以分隔线隔开
+    fn new_tray_menu(sections: &[&[&MenuItem]]) -> Result<Menu> {
+        let menu = Menu::new();
+
+        for (i, section) in sections.iter().enumerate() {
+            if i > 0 {
+                menu.append(&PredefinedMenuItem::separator())?;
+            }
+            for item in section.iter() {
+                menu.append(*item)?;
+            }
+        }
+
+        Ok(menu)
+    }
+
+    /// 与后台的定时更新使用同一份配置，菜单操作前同样调用，避免使用旧的来源与壁纸库
+    fn sync_config(&mut self) {
+        match self.service.reconfigure_if_changed(&mut self.config) {
+            Ok(true) => self.refresh_menu_texts(),
+            Ok(false) => {}
+            Err(e) => error!("应用配置失败: {:?}", e),
+        }
+    }
+
+    fn refresh_menu_texts(&self) {
+        let menu = self.config.borrow().menu.clone();
+        let daily_update = match self.daily_updating {
+            Some(_) => &menu.daily_update_enabled,
+            None => &menu.enable_daily_update,
+        };
+        self.menu_item_daily_update.set_text(daily_update);
+        self.menu_item_update.set_text(&menu.update);
+        self.menu_item_learn_more.set_text(&menu.learn_more);
+        self.menu_item_open_image.set_text(&menu.open_image);
+        self.menu_item_open_folder.set_text(&menu.open_folder);
+        self.menu_item_open_log.set_text(&menu.open_log);
+        self.menu_item_copy_info.set_text(&menu.copy_info);
+        self.menu_item_exit.set_text(&menu.exit);
+    }
+
+    /// 启动时读取保存的状态，之后由 `WallpaperApplied` 事件更新
+    fn refresh_state(&mut self) {
+        let state = self.rt.block_on(self.service.state().get());
+        self.show_image(state.image, state.path);
+    }
+
+    /// 记录当前壁纸的信息与路径，并更新提示文字与菜单项
+    fn show_image(&mut self, image: Option<ImageMeta>, path: Option<PathBuf>) {
+        self.menu_item_open_image.set_enabled(path.is_some());
+        self.menu_item_copy_info.set_enabled(image.is_some());
+        self.menu_item_learn_more
+            .set_enabled(image.as_ref().is_some_and(|image| !image.link.is_empty()));
+        self.image = image;
+        self.image_path = path;
+
+        let tooltip = self
+            .image
+            .as_ref()
+            .map(Self::image_tooltip)
+            .unwrap_or_else(|| TOOLTIP.to_string());
+        if tooltip == self.tooltip {
+            return;
+        }
+
+        if let Err(e) = self.tray_icon.set_tooltip(Some(&tooltip)) {
+            error!("Set tooltip error: {:?}", e);
+        }
+        let header = self
+            .image
+            .as_ref()
+            .map(Self::image_header)
+            .unwrap_or_else(|| TOOLTIP.to_string());
+        self.menu_item_header.set_text(header);
+        self.tooltip = tooltip;
+    }
+
+    /// 标题、版权与日期
+    fn image_lines(image: &ImageMeta) -> Vec<String> {
+        let date = image.date().map(|date| date.to_string());
+        [
+            Some(image.title.clone()),
+            Some(image.copyright.clone()),
+            date,
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|line| !line.is_empty())
+        .collect()
+    }
+
+    fn image_tooltip(image: &ImageMeta) -> String {
+        let tooltip = Self::image_lines(image).join("\n");
+
+        tooltip.chars().take(TOOLTIP_MAX_CHARS).collect()
+    }
+
+    /// 菜单项只显示一行，`&` 需要转义以免被当作快捷键
+    fn image_header(image: &ImageMeta) -> String {
+        let header = Self::image_lines(image).join(" · ");
+        let mut chars = header.chars();
+        let mut header = chars.by_ref().take(HEADER_MAX_CHARS).collect::<String>();
+        if chars.next().is_some() {
+            header.push('…');
+        }
+
+        header.replace('&', "&&")
+    }
+
+    /// 复制到剪贴板的文字，比提示文字多出链接与文件路径
+    fn image_info(image: &ImageMeta, path: Option<&Path>) -> String {
+        let date = image.date().map(|date| date.to_string());
+        let path = path.map(|path| path.display().to_string());
+        [
+            Some(image.title.as_str()),
+            Some(image.copyright.as_str()),
+            date.as_deref(),
+            Some(image.link.as_str()),
+            path.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+    }
+
+    fn learn_more(&self) {
+        if let Some(image) = self.image.as_ref().filter(|image| !image.link.is_empty())
+            && let Err(e) = shell::open(&image.link)
+        {
+            error!("打开 {} 失败: {:?}", image.link, e);
+        }
+    }
+
+    fn open_image(&self) {
+        if let Some(path) = &self.image_path {
+            open(path);
+        }
+    }
+
+    /// 当前壁纸所在的目录，尚未更新过时为壁纸库
+    fn open_folder(&self) {
+        let dir = self
+            .image_path
+            .as_deref()
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| self.service.archive().dir().to_path_buf());
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            error!("创建目录失败 {}: {:?}", dir.display(), e);
+            return;
+        }
+        open(&dir);
+    }
+
+    /// 没有日志文件时打开日志目录
+    fn open_log(&self) {
+        if let Some(dir) = &self.log_dir {
+            open(&latest_log(dir).unwrap_or_else(|| dir.clone()));
+        }
+    }
+
+    fn copy_info(&self) {
+        let Some(image) = &self.image else {
+            return;
+        };
+        let info = Self::image_info(image, self.image_path.as_deref());
+        if let Err(e) = shell::copy_text(&info) {
+            error!("复制壁纸信息失败: {:?}", e);
+        }
+    }
+
+    fn load_icon() -> Result<Icon> {
+        let icon_bytes = include_bytes!("../assets/favicon.ico");
+        let icon_dyn_image = image::load_from_memory(icon_bytes)?;
+        let rgba = icon_dyn_image.to_rgba8();
+        let (width, height) = icon_dyn_image.dimensions();
+
+        Ok(Icon::from_rgba(rgba.into_raw(), width, height)?)
+    }
+}
+
+impl ApplicationHandler<UserEvent> for Application {
+    fn resumed(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop) {}
+
+    fn window_event(
+        &mut self,
+        _event_loop: &winit::event_loop::ActiveEventLoop,
+        _window_id: winit::window::WindowId,
+        _event: winit::event::WindowEvent,
+    ) {
+    }
+
+    fn new_events(
+        &mut self,
+        _event_loop: &winit::event_loop::ActiveEventLoop,
+        cause: winit::event::StartCause,
+    ) {
+        if winit::event::StartCause::Init == cause {
+            self.refresh_state();
+
+            let menu_event = MenuEvent {
+                id: self.menu_item_daily_update.id().clone(),
+            };
+            if let Err(e) = self
+                .user_event_proxy
+                .send_event(UserEvent::MenuEvent(menu_event))
+            {
+                error!("Event handle error: {:?}", e);
+            }
+        }
+    }
+
+    fn user_event(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop, event: UserEvent) {
+        match event {
+            UserEvent::TrayIconEvent(_tray_icon_event) => {}
+            UserEvent::ConfigChanged => self.sync_config(),
+            UserEvent::WallpaperApplied { image, path } => {
+                self.show_image(Some(*image), Some(path));
+            }
+            UserEvent::MenuEvent(menu_event) => {
+                if menu_event.id == self.menu_item_daily_update.id() {
+                    let menu = self.config.borrow().menu.clone();
+                    match &self.daily_updating {
+                        Some(join_handle) => {
+                            join_handle.abort();
+                            self.daily_updating = None;
+                            self.menu_item_daily_update
+                                .set_text(&menu.enable_daily_update);
+                        }
+                        None => {
+                            self.daily_updating = Some(
+                                self.rt.spawn(
+                                    self.service
+                                        .clone()
+                                        .handle_enable_daily_updating(self.config.clone()),
+                                ),
+                            );
+                            self.menu_item_daily_update
+                                .set_text(&menu.daily_update_enabled);
+                        }
+                    }
+                }
+
+                if menu_event.id == self.menu_item_update.id() {
+                    self.sync_config();
+                    self.rt
+                        .spawn(self.service.clone().handle_update_wallpaper());
+                }
+
+                if menu_event.id == self.menu_item_learn_more.id() {
+                    self.learn_more();
+                }
+
+                if menu_event.id == self.menu_item_open_image.id() {
+                    self.open_image();
+                }
+
+                if menu_event.id == self.menu_item_open_folder.id() {
+                    self.sync_config();
+                    self.open_folder();
+                }
+
+                if menu_event.id == self.menu_item_open_log.id() {
+                    self.open_log();
+                }
+
+                if menu_event.id == self.menu_item_copy_info.id() {
+                    self.copy_info();
+                }
+
+                if menu_event.id == self.menu_item_exit.id() {
+                    std::process::exit(0);
+                }
+            }
+        };
+    }
+}
+
+fn open(path: &Path) {
+    if let Err(e) = shell::open(path) {
+        error!("打开 {} 失败: {:?}", path.display(), e);
+    }
+}
+
+/// 日志文件名中的日期使文件名顺序与时间顺序一致
+fn latest_log(dir: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    name.starts_with(LOG_FILE_PREFIX) && name.ends_with(LOG_FILE_SUFFIX)
+                })
+        })
+        .max()
+}
@@ -0,0 +1,406 @@
+use super::WallpaperSetter;
+use crate::Error;
+use reqwest::Url;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Child;
+use std::process::Command;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use tracing::info;
+use tracing::warn;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Desktop {
+    Gnome,
+    Kde,
+    Xfce,
+    Wlroots,
+    X11,
+    Unknown,
+}
+
+impl Desktop {
+    pub fn detect() -> Self {
+        Self::detect_from(|key| std::env::var(key).ok())
+    }
+
+    pub fn detect_from(env: impl Fn(&str) -> Option<String>) -> Self {
+        let is_set = |key: &str| env(key).is_some_and(|value| !value.is_empty());
+
+        let current_desktop = env("XDG_CURRENT_DESKTOP")
+            .unwrap_or_default()
+            .to_lowercase();
+        for name in current_desktop.split(':') {
+            match name {
+                "gnome" | "unity" | "budgie" | "pantheon" => return Self::Gnome,
+                "kde" => return Self::Kde,
+                "xfce" => return Self::Xfce,
+                "sway" | "hyprland" | "river" | "wayfire" | "labwc" | "niri" => {
+                    return Self::Wlroots;
+                }
+                _ => {}
+            }
+        }
+
+        if is_set("SWAYSOCK") || is_set("WAYLAND_DISPLAY") {
+            return Self::Wlroots;
+        }
+        if is_set("DISPLAY") {
+            return Self::X11;
+        }
+
+        Self::Unknown
+    }
+}
+
+pub(super) fn setter_for(desktop: &Desktop) -> Option<Arc<dyn WallpaperSetter>> {
+    info!("检测到桌面环境: {:?}", desktop);
+
+    match desktop {
+        Desktop::Gnome => GnomeSetter::detect().map(|s| Arc::new(s) as _),
+        Desktop::Kde => KdeSetter::detect().map(|s| Arc::new(s) as _),
+        Desktop::Xfce => XfceSetter::detect().map(|s| Arc::new(s) as _),
+        Desktop::Wlroots => SwwwSetter::detect()
+            .map(|s| Arc::new(s) as _)
+            .or_else(|| SwayBgSetter::detect().map(|s| Arc::new(s) as _)),
+        Desktop::X11 => X11Setter::detect().map(|s| Arc::new(s) as _),
+        Desktop::Unknown => None,
+    }
+}
+
+pub struct GnomeSetter {
+    gsettings: PathBuf,
+}
+
+impl GnomeSetter {
+    pub fn detect() -> Option<Self> {
+        Some(Self {
+            gsettings: find_program("gsettings")?,
+        })
+    }
+}
+
+impl WallpaperSetter for GnomeSetter {
+    fn name(&self) -> &'static str {
+        "gnome"
+    }
+
+    fn set_wallpaper(&self, path: &Path) -> Result<(), Error> {
+        info!("应用壁纸");
+
+        let uri = file_uri(path)?;
+        run(Command::new(&self.gsettings).args([
+            "set",
+            "org.gnome.desktop.background",
+            "picture-uri",
+            &uri,
+        ]))?;
+        if let Err(e) = run(Command::new(&self.gsettings).args([
+            "set",
+            "org.gnome.desktop.background",
+            "picture-uri-dark",
+            &uri,
+        ])) {
+            warn!("设置深色模式壁纸失败: {:?}", e);
+        }
+
+        Ok(())
+    }
+}
+
+enum KdeProgram {
+    ApplyWallpaperImage(PathBuf),
+    Qdbus(PathBuf),
+}
+
+pub struct KdeSetter {
+    program: KdeProgram,
+}
+
+impl KdeSetter {
+    pub fn detect() -> Option<Self> {
+        if let Some(program) = find_program("plasma-apply-wallpaperimage") {
+            return Some(Self {
+                program: KdeProgram::ApplyWallpaperImage(program),
+            });
+        }
+
+        ["qdbus6", "qdbus-qt6", "qdbus", "qdbus-qt5"]
+            .into_iter()
+            .find_map(find_program)
+            .map(|program| Self {
+                program: KdeProgram::Qdbus(program),
+            })
+    }
+}
+
+impl WallpaperSetter for KdeSetter {
+    fn name(&self) -> &'static str {
+        "kde"
+    }
+
+    fn set_wallpaper(&self, path: &Path) -> Result<(), Error> {
+        info!("应用壁纸");
+
+        let path = absolute(path)?;
+        match &self.program {
+            KdeProgram::ApplyWallpaperImage(program) => run(Command::new(program).arg(&path)),
+            KdeProgram::Qdbus(program) => {
+                let uri = file_uri(&path)?.replace('\\', "\\\\").replace('"', "\\\"");
+                let script = format!(
+                    "desktops().forEach(d => {{ \
+                     d.wallpaperPlugin = \"org.kde.image\"; \
+                     d.currentConfigGroup = [\"Wallpaper\", \"org.kde.image\", \"General\"]; \
+                     d.writeConfig(\"Image\", \"{}\"); }});",
+                    uri
+                );
+                run(Command::new(program).args([
+                    "org.kde.plasmashell",
+                    "/PlasmaShell",
+                    "org.kde.PlasmaShell.evaluateScript",
+                    &script,
+                ]))
+            }
+        }
+    }
+}
+
+pub struct XfceSetter {
+    xfconf_query: PathBuf,
+}
+
+impl XfceSetter {
+    const CHANNEL: &str = "xfce4-desktop";
+
+    pub fn detect() -> Option<Self> {
+        Some(Self {
+            xfconf_query: find_program("xfconf-query")?,
+        })
+    }
+}
+
+impl WallpaperSetter for XfceSetter {
+    fn name(&self) -> &'static str {
+        "xfce"
+    }
+
+    /// 每个显示器与工作区各有一个 `.../last-image` 属性，全部设置
+    fn set_wallpaper(&self, path: &Path) -> Result<(), Error> {
+        info!("应用壁纸");
+
+        let path = absolute(path)?;
+        let properties =
+            output(Command::new(&self.xfconf_query).args(["-c", Self::CHANNEL, "-l"]))?;
+        let properties = properties
+            .lines()
+            .map(str::trim)
+            .filter(|property| property.ends_with("/last-image"))
+            .collect::<Vec<_>>();
+        if properties.is_empty() {
+            return Err(Error::Platform(
+                "未找到 XFCE 桌面的壁纸属性 (last-image)".to_string(),
+            ));
+        }
+
+        for property in properties {
+            run(Command::new(&self.xfconf_query)
+                .args(["-c", Self::CHANNEL, "-p", property, "-s"])
+                .arg(&path))?;
+        }
+
+        Ok(())
+    }
+}
+
+pub struct SwwwSetter {
+    swww: PathBuf,
+}
+
+impl SwwwSetter {
+    pub fn detect() -> Option<Self> {
+        Some(Self {
+            swww: find_program("swww")?,
+        })
+    }
+}
+
+impl WallpaperSetter for SwwwSetter {
+    fn name(&self) -> &'static str {
+        "swww"
+    }
+
+    fn set_wallpaper(&self, path: &Path) -> Result<(), Error> {
+        info!("应用壁纸");
+
+        run(Command::new(&self.swww).arg("img").arg(absolute(path)?))
+    }
+}
+
+pub struct SwayBgSetter {
+    swaymsg: Option<PathBuf>,
+    swaybg: Option<PathBuf>,
+    child: Mutex<Option<Child>>,
+}
+
+impl SwayBgSetter {
+    pub fn detect() -> Option<Self> {
+        let swaymsg = std::env::var_os("SWAYSOCK").and_then(|_| find_program("swaymsg"));
+        let swaybg = find_program("swaybg");
+        if swaymsg.is_none() && swaybg.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            swaymsg,
+            swaybg,
+            child: Mutex::new(None),
+        })
+    }
+}
+
+impl WallpaperSetter for SwayBgSetter {
+    fn name(&self) -> &'static str {
+        "swaybg"
+    }
+
+    fn set_wallpaper(&self, path: &Path) -> Result<(), Error> {
+        info!("应用壁纸");
+
+        let path = absolute(path)?;
+        if let Some(swaymsg) = &self.swaymsg {
+            // swaymsg 将参数拼接为一条 sway 命令，路径中的空格需要加引号
+            return run(Command::new(swaymsg)
+                .args(["output", "*", "bg"])
+                .arg(sway_quote(&path))
+                .arg("fill"));
+        }
+
+        let swaybg = self
+            .swaybg
+            .as_ref()
+            .ok_or_else(|| Error::Unsupported("未找到 swaybg".to_string()))?;
+
+        let mut child = self.child.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(mut old_child) = child.take() {
+            old_child.kill().ok();
+            old_child.wait().ok();
+        }
+        // 其他进程（例如定时执行的 `update`）启动的 swaybg 也要结束，否则会越积越多
+        if let Some(pkill) = find_program("pkill")
+            && let Err(e) = Command::new(pkill)
+                .args(["-x", "swaybg"])
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+        {
+            warn!("结束已有的 swaybg 失败: {:?}", e);
+        }
+
+        let new_child = Command::new(swaybg)
+            .arg("-i")
+            .arg(&path)
+            .args(["-m", "fill"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(Error::io(swaybg))?;
+        *child = Some(new_child);
+
+        Ok(())
+    }
+}
+
+enum X11Program {
+    Feh(PathBuf),
+    Xwallpaper(PathBuf),
+}
+
+pub struct X11Setter {
+    program: X11Program,
+}
+
+impl X11Setter {
+    pub fn detect() -> Option<Self> {
+        if let Some(program) = find_program("feh") {
+            return Some(Self {
+                program: X11Program::Feh(program),
+            });
+        }
+
+        find_program("xwallpaper").map(|program| Self {
+            program: X11Program::Xwallpaper(program),
+        })
+    }
+}
+
+impl WallpaperSetter for X11Setter {
+    fn name(&self) -> &'static str {
+        match self.program {
+            X11Program::Feh(_) => "feh",
+            X11Program::Xwallpaper(_) => "xwallpaper",
+        }
+    }
+
+    fn set_wallpaper(&self, path: &Path) -> Result<(), Error> {
+        info!("应用壁纸");
+
+        let path = absolute(path)?;
+        match &self.program {
+            X11Program::Feh(program) => run(Command::new(program).arg("--bg-fill").arg(&path)),
+            X11Program::Xwallpaper(program) => run(Command::new(program).arg("--zoom").arg(&path)),
+        }
+    }
+}
+
+fn find_program(name: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+fn absolute(path: &Path) -> Result<PathBuf, Error> {
+    std::fs::canonicalize(path).map_err(Error::io(path))
+}
+
+/// 例如 `"/home/me/My Pictures/a.jpg"`
+fn sway_quote(path: &Path) -> String {
+    let path = path.display().to_string();
+    format!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn file_uri(path: &Path) -> Result<String, Error> {
+    let path = absolute(path)?;
+    let uri = Url::from_file_path(&path)
+        .map_err(|_| Error::io(&path)(io::ErrorKind::InvalidInput.into()))?;
+
+    Ok(uri.to_string())
+}
+
+fn run(command: &mut Command) -> Result<(), Error> {
+    output(command).map(|_| ())
+}
+
+/// 执行失败时返回包含标准错误的 `Error::Platform`，成功时返回标准输出
+fn output(command: &mut Command) -> Result<String, Error> {
+    let output = command
+        .stdin(Stdio::null())
+        .output()
+        .map_err(Error::io(command.get_program()))?;
+    if !output.status.success() {
+        return Err(Error::Platform(format!(
+            "{:?} 执行失败 ({}): {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
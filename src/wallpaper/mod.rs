@@ -0,0 +1,75 @@
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(windows)]
+mod windows;
+
+#[cfg(target_os = "linux")]
+pub use linux::Desktop;
+#[cfg(target_os = "linux")]
+pub use linux::GnomeSetter;
+#[cfg(target_os = "linux")]
+pub use linux::KdeSetter;
+#[cfg(target_os = "linux")]
+pub use linux::SwayBgSetter;
+#[cfg(target_os = "linux")]
+pub use linux::SwwwSetter;
+#[cfg(target_os = "linux")]
+pub use linux::X11Setter;
+#[cfg(target_os = "linux")]
+pub use linux::XfceSetter;
+#[cfg(windows)]
+pub use windows::Win32Setter;
+
+use crate::Error;
+use std::path::Path;
+use std::sync::Arc;
+
+pub trait WallpaperSetter: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn set_wallpaper(&self, path: &Path) -> Result<(), Error>;
+}
+
+pub struct UnsupportedSetter {
+    reason: String,
+}
+
+impl UnsupportedSetter {
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+        }
+    }
+}
+
+impl WallpaperSetter for UnsupportedSetter {
+    fn name(&self) -> &'static str {
+        "unsupported"
+    }
+
+    fn set_wallpaper(&self, _path: &Path) -> Result<(), Error> {
+        Err(Error::Unsupported(self.reason.clone()))
+    }
+}
+
+#[cfg(windows)]
+pub fn detect_setter() -> Arc<dyn WallpaperSetter> {
+    Arc::new(Win32Setter)
+}
+
+#[cfg(target_os = "linux")]
+pub fn detect_setter() -> Arc<dyn WallpaperSetter> {
+    let desktop = Desktop::detect();
+    match linux::setter_for(&desktop) {
+        Some(setter) => setter,
+        None => Arc::new(UnsupportedSetter::new(format!(
+            "未找到适用于 {:?} 的壁纸设置方式",
+            desktop
+        ))),
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+pub fn detect_setter() -> Arc<dyn WallpaperSetter> {
+    Arc::new(UnsupportedSetter::new("当前平台不支持设置壁纸"))
+}
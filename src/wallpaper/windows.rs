@@ -0,0 +1,38 @@
+use super::WallpaperSetter;
+use crate::Error;
+use std::os::windows::ffi::OsStrExt;
+use std::path::Path;
+use tracing::info;
+use windows::Win32::UI::WindowsAndMessaging::SPI_SETDESKWALLPAPER;
+use windows::Win32::UI::WindowsAndMessaging::SPIF_SENDCHANGE;
+use windows::Win32::UI::WindowsAndMessaging::SPIF_UPDATEINIFILE;
+use windows::Win32::UI::WindowsAndMessaging::SystemParametersInfoW;
+
+pub struct Win32Setter;
+
+impl WallpaperSetter for Win32Setter {
+    fn name(&self) -> &'static str {
+        "win32"
+    }
+
+    fn set_wallpaper(&self, path: &Path) -> Result<(), Error> {
+        info!("应用壁纸");
+
+        let wide: Vec<u16> = path
+            .as_os_str()
+            .encode_wide()
+            .chain(std::iter::once(0))
+            .collect();
+
+        unsafe {
+            SystemParametersInfoW(
+                SPI_SETDESKWALLPAPER,
+                0,
+                Some(wide.as_ptr() as _),
+                SPIF_UPDATEINIFILE | SPIF_SENDCHANGE,
+            )?;
+        }
+
+        Ok(())
+    }
+}
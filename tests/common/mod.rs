@@ -0,0 +1,376 @@
+use axum::Router;
+use axum::body::Body;
+use axum::extract::Query;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::http::HeaderValue;
+use axum::http::StatusCode;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::routing::get;
+use bingwallpaper::Archive;
+use bingwallpaper::BingSource;
+use bingwallpaper::Error;
+use bingwallpaper::HpRequest;
+use bingwallpaper::Resolution;
+use bingwallpaper::RetryPolicy;
+use bingwallpaper::StallTimeout;
+use bingwallpaper::Timeouts;
+use bingwallpaper::WallpaperService;
+use bingwallpaper::WallpaperSetter;
+use futures_util::stream;
+use image::ImageFormat;
+use image::RgbImage;
+use serde_json::Value;
+use serde_json::json;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::io::Cursor;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+/// HPImageArchive 接口的返回内容
+#[derive(Debug, Clone)]
+pub enum Metadata {
+    Images(Vec<Value>),
+    Malformed,
+}
+
+#[derive(Debug)]
+pub struct Behavior {
+    pub metadata: Metadata,
+    /// 设置后返回 `ETag` 并处理 `If-None-Match`
+    pub etag: Option<String>,
+    pub metadata_delay: Duration,
+    /// 依次用于接下来的请求
+    pub metadata_fail: VecDeque<StatusCode>,
+    pub image_fail: VecDeque<StatusCode>,
+    /// 返回 404 的图片 id
+    pub missing: HashSet<String>,
+    /// 接下来的若干次图片响应只发送一半后断开
+    pub truncate: usize,
+    /// 图片响应每 1 KiB 之间的间隔
+    pub image_delay: Duration,
+    /// 代替默认 JPEG 返回的内容
+    pub image_body: Option<Vec<u8>>,
+    pub image_type: &'static str,
+}
+
+impl Default for Behavior {
+    fn default() -> Self {
+        Self {
+            metadata: Metadata::Images(vec![hp_image(0)]),
+            etag: None,
+            metadata_delay: Duration::ZERO,
+            metadata_fail: VecDeque::new(),
+            image_fail: VecDeque::new(),
+            missing: HashSet::new(),
+            truncate: 0,
+            image_delay: Duration::ZERO,
+            image_body: None,
+            image_type: "image/jpeg",
+        }
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    behavior: Mutex<Behavior>,
+    hits: Mutex<Vec<String>>,
+}
+
+pub struct FakeBing {
+    url: String,
+    shared: Arc<Shared>,
+}
+
+impl FakeBing {
+    pub async fn start() -> Self {
+        let shared = Arc::new(Shared::default());
+        let app = Router::new()
+            .route("/HPImageArchive.aspx", get(metadata))
+            .route("/th", get(image))
+            .with_state(shared.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        Self { url, shared }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn set(&self, f: impl FnOnce(&mut Behavior)) {
+        f(&mut self.shared.behavior.lock().unwrap());
+    }
+
+    /// 收到的请求，参数按名称排序，例如 `/th?id=OHR.Day0_1920x1080.jpg&pid=hp&range=bytes=100-`
+    pub fn hits(&self) -> Vec<String> {
+        self.shared.hits.lock().unwrap().clone()
+    }
+
+    pub fn count(&self, path: &str) -> usize {
+        self.hits()
+            .iter()
+            .filter(|hit| hit.starts_with(path))
+            .count()
+    }
+
+    pub fn request(&self) -> HpRequest {
+        HpRequest::builder().host(&self.url).build().unwrap()
+    }
+}
+
+/// 第 `day` 天前的壁纸，hsh 为 `h{day}`
+pub fn hp_image(day: u32) -> Value {
+    let date = time::OffsetDateTime::now_utc().date() - time::Duration::days(day.into());
+    let date = format!(
+        "{:04}{:02}{:02}",
+        date.year(),
+        u8::from(date.month()),
+        date.day()
+    );
+    json!({
+        "startdate": date,
+        "fullstartdate": format!("{}1600", date),
+        "enddate": date,
+        "url": format!("/th?id=OHR.Day{}_1920x1080.jpg&pid=hp", day),
+        "urlbase": format!("/th?id=OHR.Day{}", day),
+        "copyright": format!("Day {} (© Someone)", day),
+        "copyrightlink": "https://www.bing.com/search?q=test",
+        "title": format!("Day {}", day),
+        "hsh": format!("h{}", day),
+    })
+}
+
+/// 符合最小尺寸要求的 JPEG
+pub fn jpeg() -> &'static [u8] {
+    static JPEG: OnceLock<Vec<u8>> = OnceLock::new();
+    JPEG.get_or_init(|| {
+        let image = RgbImage::from_fn(800, 450, |x, y| {
+            image::Rgb([(x % 256) as u8, (y % 256) as u8, 128])
+        });
+        let mut bytes = Cursor::new(Vec::new());
+        image.write_to(&mut bytes, ImageFormat::Jpeg).unwrap();
+        bytes.into_inner()
+    })
+}
+
+fn record(shared: &Shared, path: &str, query: &HashMap<String, String>) {
+    let mut query = query.iter().collect::<Vec<_>>();
+    query.sort();
+    let query = query
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("&");
+    shared
+        .hits
+        .lock()
+        .unwrap()
+        .push(format!("{}?{}", path, query));
+}
+
+async fn metadata(
+    State(shared): State<Arc<Shared>>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    record(&shared, "/HPImageArchive.aspx", &query);
+    if let Some(status) = shared.behavior.lock().unwrap().metadata_fail.pop_front() {
+        return status.into_response();
+    }
+
+    let (metadata, etag, delay) = {
+        let behavior = shared.behavior.lock().unwrap();
+        (
+            behavior.metadata.clone(),
+            behavior.etag.clone(),
+            behavior.metadata_delay,
+        )
+    };
+    tokio::time::sleep(delay).await;
+
+    if let Some(etag) = &etag
+        && headers
+            .get(header::IF_NONE_MATCH)
+            .and_then(|v| v.to_str().ok())
+            == Some(etag)
+    {
+        return StatusCode::NOT_MODIFIED.into_response();
+    }
+
+    let body = match metadata {
+        Metadata::Images(images) => {
+            let idx = query
+                .get("idx")
+                .and_then(|idx| idx.parse().ok())
+                .unwrap_or(0);
+            let n = query.get("n").and_then(|n| n.parse().ok()).unwrap_or(1);
+            let images = images.into_iter().skip(idx).take(n).collect::<Vec<_>>();
+            json!({ "images": images }).to_string()
+        }
+        Metadata::Malformed => "<html>not json</html>".to_string(),
+    };
+    let mut response = ([(header::CONTENT_TYPE, "application/json")], body).into_response();
+    if let Some(etag) = etag {
+        response
+            .headers_mut()
+            .insert(header::ETAG, HeaderValue::from_str(&etag).unwrap());
+    }
+    response
+}
+
+async fn image(
+    State(shared): State<Arc<Shared>>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    let mut logged = query.clone();
+    if let Some(range) = headers.get(header::RANGE).and_then(|v| v.to_str().ok()) {
+        logged.insert("range".to_string(), range.to_string());
+    }
+    record(&shared, "/th", &logged);
+    if let Some(status) = shared.behavior.lock().unwrap().image_fail.pop_front() {
+        return status.into_response();
+    }
+
+    let id = query.get("id").cloned().unwrap_or_default();
+    let (missing, truncate, delay, bytes, content_type) = {
+        let mut behavior = shared.behavior.lock().unwrap();
+        let truncate = behavior.truncate > 0;
+        if truncate {
+            behavior.truncate -= 1;
+        }
+        (
+            behavior.missing.contains(&id),
+            truncate,
+            behavior.image_delay,
+            behavior
+                .image_body
+                .clone()
+                .unwrap_or_else(|| jpeg().to_vec()),
+            behavior.image_type,
+        )
+    };
+    if missing {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let start = headers
+        .get(header::RANGE)
+        .and_then(|range| range.to_str().ok())
+        .and_then(|range| range.strip_prefix("bytes="))
+        .and_then(|range| range.strip_suffix('-'))
+        .and_then(|start| start.parse::<usize>().ok())
+        .filter(|&start| start < bytes.len());
+    let (status, body) = match start {
+        Some(start) => (StatusCode::PARTIAL_CONTENT, &bytes[start..]),
+        None => (StatusCode::OK, &bytes[..]),
+    };
+
+    let sent = if truncate { body.len() / 2 } else { body.len() };
+    let chunks = body[..sent]
+        .chunks(1024)
+        .map(|chunk| Ok::<_, std::io::Error>(chunk.to_vec()))
+        .chain(truncate.then(|| {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionReset,
+                "truncated",
+            ))
+        }))
+        .collect::<Vec<_>>();
+    let stream = futures_util::StreamExt::then(stream::iter(chunks), move |chunk| async move {
+        tokio::time::sleep(delay).await;
+        chunk
+    });
+
+    let mut response = Response::new(Body::from_stream(stream));
+    *response.status_mut() = status;
+    let headers = response.headers_mut();
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    headers.insert(header::CONTENT_LENGTH, HeaderValue::from(body.len()));
+    headers.insert(header::ETAG, HeaderValue::from_static("\"image\""));
+    if let Some(start) = start {
+        let range = format!("bytes {}-{}/{}", start, bytes.len() - 1, bytes.len());
+        headers.insert(
+            header::CONTENT_RANGE,
+            HeaderValue::from_str(&range).unwrap(),
+        );
+    }
+    response
+}
+
+/// 只记录要应用的壁纸，不需要桌面环境
+#[derive(Default)]
+pub struct RecordingSetter {
+    applied: Mutex<Vec<PathBuf>>,
+}
+
+impl RecordingSetter {
+    pub fn applied(&self) -> Vec<PathBuf> {
+        self.applied.lock().unwrap().clone()
+    }
+}
+
+impl WallpaperSetter for RecordingSetter {
+    fn name(&self) -> &'static str {
+        "recording"
+    }
+
+    fn set_wallpaper(&self, path: &Path) -> Result<(), Error> {
+        if !path.is_file() {
+            return Err(Error::io(path)(std::io::ErrorKind::NotFound.into()));
+        }
+        self.applied.lock().unwrap().push(path.to_path_buf());
+        Ok(())
+    }
+}
+
+pub struct Harness {
+    pub server: FakeBing,
+    pub setter: Arc<RecordingSetter>,
+    pub dir: tempfile::TempDir,
+}
+
+impl Harness {
+    pub async fn start() -> Self {
+        Self {
+            server: FakeBing::start().await,
+            setter: Arc::new(RecordingSetter::default()),
+            dir: tempfile::tempdir().unwrap(),
+        }
+    }
+
+    pub fn service(&self) -> WallpaperService {
+        self.service_with(None)
+    }
+
+    /// 重试与超时都缩短，以免测试过慢
+    pub fn service_with(&self, resolution: Option<Resolution>) -> WallpaperService {
+        WallpaperService::with_client(reqwest::Client::new())
+            .with_source(Arc::new(BingSource::new(self.server.request(), resolution)))
+            .with_archive(Archive::new(self.dir.path().to_path_buf()))
+            .with_setter(self.setter.clone())
+            .with_retry(RetryPolicy {
+                max_attempts: 3,
+                base_delay: Duration::from_millis(10),
+                max_delay: Duration::from_millis(50),
+                jitter: false,
+            })
+            .with_timeouts(Timeouts {
+                metadata: Duration::from_millis(500),
+                download: Some(Duration::from_secs(5)),
+                stall: StallTimeout::default(),
+            })
+    }
+}
@@ -0,0 +1,90 @@
+use bingwallpaper::Config;
+use bingwallpaper::WallpaperError;
+
+fn parse(toml: &str) -> Result<Config, WallpaperError> {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, toml).unwrap();
+    let config = Config::load(&path)?;
+    config.validate()?;
+    Ok(config)
+}
+
+#[test]
+fn passes_bing_n_to_request() {
+    let config = parse("[bing]\nidx = 1\nn = 3\n").unwrap();
+
+    let url = config.request().unwrap().url();
+    let query = url.query().unwrap();
+    assert!(query.contains("idx=1"), "{}", query);
+    assert!(query.contains("n=3"), "{}", query);
+}
+
+#[test]
+fn rejects_bing_n_out_of_range() {
+    for n in [0, 9] {
+        assert!(matches!(
+            parse(&format!("[bing]\nn = {}\n", n)),
+            Err(WallpaperError::Config(_))
+        ));
+    }
+    assert!(parse("[bing]\nn = 8\n").is_ok());
+}
+
+#[test]
+fn expands_home_in_paths() {
+    let home = dirs::home_dir().unwrap();
+    let config = parse(
+        "[archive]\ndir = \"~/Pictures/BingWallpaper\"\n\n[log]\ndir = \"~\"\n\n\
+         [network]\nca_certs = [\"~/ca.pem\", \"/etc/ca.pem\", \"~user/ca.pem\"]\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        config.archive.dir,
+        Some(home.join("Pictures").join("BingWallpaper"))
+    );
+    assert_eq!(config.log.dir, Some(home.clone()));
+    assert_eq!(
+        config.network.ca_certs,
+        vec![
+            home.join("ca.pem"),
+            "/etc/ca.pem".into(),
+            "~user/ca.pem".into()
+        ]
+    );
+}
+
+#[test]
+fn backfill_markets_become_bing_sources() {
+    let config = parse("[archive]\nbackfill = true\nmarkets = [\"en-US\", \"ja-JP\"]\n").unwrap();
+
+    let keys = config
+        .bing_sources(&config.archive.markets)
+        .unwrap()
+        .iter()
+        .map(|source| source.key())
+        .collect::<Vec<_>>();
+    assert_eq!(keys.len(), 2);
+    assert!(keys[0].contains("mkt=en-US"));
+    assert!(keys[1].contains("mkt=ja-JP"));
+
+    assert!(matches!(
+        parse("[archive]\nmarkets = [\"xx-XX\"]\n"),
+        Err(WallpaperError::Config(_))
+    ));
+}
+
+#[test]
+fn validates_after_overrides() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, "[source]\nkind = \"unsplash\"\n").unwrap();
+
+    let mut config = Config::load(&path).unwrap();
+    assert!(matches!(config.validate(), Err(WallpaperError::Config(_))));
+
+    // 例如 `--source bing`
+    config.source.kind = "bing".to_string();
+    config.validate().unwrap();
+}
@@ -0,0 +1,44 @@
+#![cfg(target_os = "linux")]
+
+use bingwallpaper::wallpaper::Desktop;
+use std::collections::HashMap;
+
+fn detect(env: &[(&str, &str)]) -> Desktop {
+    let env = env
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect::<HashMap<_, _>>();
+    Desktop::detect_from(|key| env.get(key).cloned())
+}
+
+#[test]
+fn detects_desktop_from_env() {
+    let cases: &[(&[(&str, &str)], Desktop)] = &[
+        (&[("XDG_CURRENT_DESKTOP", "GNOME")], Desktop::Gnome),
+        (&[("XDG_CURRENT_DESKTOP", "ubuntu:GNOME")], Desktop::Gnome),
+        (&[("XDG_CURRENT_DESKTOP", "KDE")], Desktop::Kde),
+        (&[("XDG_CURRENT_DESKTOP", "XFCE")], Desktop::Xfce),
+        (&[("XDG_CURRENT_DESKTOP", "sway")], Desktop::Wlroots),
+        (&[("XDG_CURRENT_DESKTOP", "Hyprland")], Desktop::Wlroots),
+        (
+            &[("SWAYSOCK", "/run/user/1000/sway-ipc.sock")],
+            Desktop::Wlroots,
+        ),
+        (&[("WAYLAND_DISPLAY", "wayland-1")], Desktop::Wlroots),
+        (&[("DISPLAY", ":0")], Desktop::X11),
+        (
+            &[("XDG_CURRENT_DESKTOP", "i3"), ("DISPLAY", ":0")],
+            Desktop::X11,
+        ),
+        (&[("XDG_CURRENT_DESKTOP", "i3")], Desktop::Unknown),
+        (
+            &[("WAYLAND_DISPLAY", ""), ("DISPLAY", "")],
+            Desktop::Unknown,
+        ),
+        (&[], Desktop::Unknown),
+    ];
+
+    for (env, expected) in cases {
+        assert_eq!(detect(env), *expected, "{:?}", env);
+    }
+}
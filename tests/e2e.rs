@@ -0,0 +1,574 @@
+mod common;
+
+use axum::http::StatusCode;
+use bingwallpaper::Archive;
+use bingwallpaper::Config;
+use bingwallpaper::Resolution;
+use bingwallpaper::Retention;
+use bingwallpaper::Schedule;
+use bingwallpaper::StallTimeout;
+use bingwallpaper::Timeouts;
+use bingwallpaper::WallpaperError;
+use common::FakeBing;
+use common::Harness;
+use common::Metadata;
+use common::hp_image;
+use common::jpeg;
+use image::ImageFormat;
+use image::RgbImage;
+use std::io::Cursor;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::SystemTime;
+use tokio::sync::mpsc;
+use tokio::sync::watch;
+
+#[tokio::test]
+async fn applies_latest_wallpaper() {
+    let harness = Harness::start().await;
+    let service = harness.service();
+
+    service.update_wallpaper().await.unwrap();
+
+    let applied = harness.setter.applied();
+    assert_eq!(applied.len(), 1);
+    assert!(applied[0].starts_with(harness.dir.path()));
+    assert!(
+        applied[0]
+            .to_string_lossy()
+            .ends_with("_zh-CN_OHR.Day0_1920x1080.jpg")
+    );
+    assert_eq!(std::fs::read(&applied[0]).unwrap(), jpeg());
+
+    let state = service.state().get().await;
+    let image = state.image.unwrap();
+    assert_eq!(image.id, "h0");
+    assert!(image.url().starts_with(harness.server.url()));
+    assert_eq!(state.path.as_ref(), Some(&applied[0]));
+    assert_eq!(service.archive().entries().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn skips_duplicate_image() {
+    let harness = Harness::start().await;
+    let service = harness.service();
+
+    service.update_wallpaper().await.unwrap();
+    service.update_wallpaper().await.unwrap();
+
+    assert_eq!(harness.setter.applied().len(), 1);
+    assert_eq!(harness.server.count("/HPImageArchive.aspx"), 2);
+    assert_eq!(harness.server.count("/th"), 1);
+}
+
+#[tokio::test]
+async fn skips_unmodified_metadata() {
+    let harness = Harness::start().await;
+    harness.server.set(|b| b.etag = Some("\"v1\"".to_string()));
+    let service = harness.service();
+
+    service.update_wallpaper().await.unwrap();
+    service.update_wallpaper().await.unwrap();
+
+    assert_eq!(harness.setter.applied().len(), 1);
+    assert_eq!(harness.server.count("/th"), 1);
+    assert_eq!(
+        service
+            .cache()
+            .metadata(&service.source().key())
+            .await
+            .unwrap()
+            .etag
+            .as_deref(),
+        Some("\"v1\"")
+    );
+}
+
+#[tokio::test]
+async fn applies_new_image_when_feed_changes() {
+    let harness = Harness::start().await;
+    let service = harness.service();
+
+    service.update_wallpaper().await.unwrap();
+    harness
+        .server
+        .set(|b| b.metadata = Metadata::Images(vec![hp_image(1)]));
+    service.update_wallpaper().await.unwrap();
+
+    let applied = harness.setter.applied();
+    assert_eq!(applied.len(), 2);
+    assert!(
+        applied[1]
+            .to_string_lossy()
+            .ends_with("OHR.Day1_1920x1080.jpg")
+    );
+    assert_eq!(service.state().get().await.image.unwrap().id, "h1");
+}
+
+#[tokio::test]
+async fn malformed_json_is_an_error() {
+    let harness = Harness::start().await;
+    harness.server.set(|b| b.metadata = Metadata::Malformed);
+    let service = harness.service();
+
+    assert!(matches!(
+        service.update_wallpaper().await,
+        Err(WallpaperError::Parse { .. })
+    ));
+
+    assert!(harness.setter.applied().is_empty());
+    assert_eq!(harness.server.count("/th"), 0);
+    assert!(service.state().get().await.image.is_none());
+}
+
+#[tokio::test]
+async fn empty_images_is_an_error() {
+    let harness = Harness::start().await;
+    harness
+        .server
+        .set(|b| b.metadata = Metadata::Images(Vec::new()));
+    let service = harness.service();
+
+    assert!(matches!(
+        service.update_wallpaper().await,
+        Err(WallpaperError::EmptyFeed(_))
+    ));
+
+    assert!(harness.setter.applied().is_empty());
+    assert_eq!(harness.server.count("/th"), 0);
+}
+
+#[tokio::test]
+async fn retries_server_errors() {
+    let harness = Harness::start().await;
+    harness.server.set(|b| {
+        b.metadata_fail = [StatusCode::INTERNAL_SERVER_ERROR; 2].into();
+        b.image_fail = [StatusCode::SERVICE_UNAVAILABLE].into();
+    });
+    let service = harness.service();
+
+    service.update_wallpaper().await.unwrap();
+
+    assert_eq!(harness.setter.applied().len(), 1);
+    assert_eq!(harness.server.count("/HPImageArchive.aspx"), 3);
+    assert_eq!(harness.server.count("/th"), 2);
+}
+
+#[tokio::test]
+async fn gives_up_after_repeated_server_errors() {
+    let harness = Harness::start().await;
+    harness
+        .server
+        .set(|b| b.metadata_fail = [StatusCode::INTERNAL_SERVER_ERROR; 5].into());
+    let service = harness.service();
+
+    assert!(matches!(
+        service.update_wallpaper().await,
+        Err(WallpaperError::HttpStatus {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            ..
+        })
+    ));
+
+    assert!(harness.setter.applied().is_empty());
+    assert_eq!(harness.server.count("/HPImageArchive.aspx"), 3);
+}
+
+#[tokio::test]
+async fn does_not_retry_not_found_metadata() {
+    let harness = Harness::start().await;
+    harness
+        .server
+        .set(|b| b.metadata_fail = [StatusCode::NOT_FOUND].into());
+    let service = harness.service();
+
+    assert!(matches!(
+        service.update_wallpaper().await,
+        Err(WallpaperError::HttpStatus {
+            status: StatusCode::NOT_FOUND,
+            ..
+        })
+    ));
+
+    assert_eq!(harness.server.count("/HPImageArchive.aspx"), 1);
+}
+
+#[tokio::test]
+async fn falls_back_to_smaller_resolution_on_404() {
+    let harness = Harness::start().await;
+    harness.server.set(|b| {
+        b.missing.insert("OHR.Day0_1920x1080.jpg".to_string());
+    });
+    let service = harness.service_with(Some("1920x1080".parse::<Resolution>().unwrap()));
+
+    service.update_wallpaper().await.unwrap();
+
+    let applied = harness.setter.applied();
+    assert_eq!(applied.len(), 1);
+    assert!(
+        applied[0]
+            .to_string_lossy()
+            .ends_with("OHR.Day0_1366x768.jpg")
+    );
+}
+
+#[tokio::test]
+async fn fails_when_every_image_is_missing() {
+    let harness = Harness::start().await;
+    harness.server.set(|b| {
+        b.missing.insert("OHR.Day0_1920x1080.jpg".to_string());
+    });
+    let service = harness.service();
+
+    assert!(matches!(
+        service.update_wallpaper().await,
+        Err(WallpaperError::EmptyFeed(_))
+    ));
+
+    assert!(harness.setter.applied().is_empty());
+    assert_eq!(harness.server.count("/th"), 1);
+}
+
+#[tokio::test]
+async fn slow_metadata_within_timeout_succeeds() {
+    let harness = Harness::start().await;
+    harness
+        .server
+        .set(|b| b.metadata_delay = Duration::from_millis(200));
+    let service = harness.service();
+
+    service.update_wallpaper().await.unwrap();
+
+    assert_eq!(harness.setter.applied().len(), 1);
+    assert_eq!(harness.server.count("/HPImageArchive.aspx"), 1);
+}
+
+#[tokio::test]
+async fn times_out_slow_metadata() {
+    let harness = Harness::start().await;
+    harness
+        .server
+        .set(|b| b.metadata_delay = Duration::from_secs(5));
+    let service = harness.service();
+
+    assert!(matches!(
+        service.update_wallpaper().await,
+        Err(WallpaperError::Network { .. })
+    ));
+
+    assert!(harness.setter.applied().is_empty());
+    assert_eq!(harness.server.count("/HPImageArchive.aspx"), 3);
+}
+
+#[tokio::test]
+async fn slow_image_download_completes() {
+    let harness = Harness::start().await;
+    harness
+        .server
+        .set(|b| b.image_delay = Duration::from_millis(2));
+    let service = harness.service();
+
+    service.update_wallpaper().await.unwrap();
+
+    let applied = harness.setter.applied();
+    assert_eq!(applied.len(), 1);
+    assert_eq!(std::fs::read(&applied[0]).unwrap(), jpeg());
+}
+
+#[tokio::test]
+async fn stalled_download_is_abandoned() {
+    let harness = Harness::start().await;
+    harness
+        .server
+        .set(|b| b.image_delay = Duration::from_millis(100));
+    let service = harness.service().with_timeouts(Timeouts {
+        metadata: Duration::from_millis(500),
+        download: None,
+        stall: StallTimeout {
+            window: Duration::from_millis(300),
+            min_bytes_per_sec: 1024 * 1024,
+        },
+    });
+
+    assert!(matches!(
+        service.update_wallpaper().await,
+        Err(WallpaperError::Interrupted { .. })
+    ));
+
+    assert!(harness.setter.applied().is_empty());
+    assert_eq!(harness.server.count("/th"), 3);
+    assert!(part_files(&harness).is_empty());
+}
+
+#[tokio::test]
+async fn concurrent_downloads_do_not_share_part_file() {
+    let harness = Harness::start().await;
+    harness
+        .server
+        .set(|b| b.image_delay = Duration::from_millis(1));
+    // 例如托盘的手动更新与定时任务同时下载
+    let (tray, daemon) = (harness.service(), harness.service());
+    let image = tray.get_latest_image().await.unwrap();
+
+    let (a, b) = tokio::join!(
+        tray.download_wallpaper(&image),
+        daemon.download_wallpaper(&image)
+    );
+
+    let (a, b) = (a.unwrap(), b.unwrap());
+    assert_eq!(a, b);
+    assert_eq!(std::fs::read(&a).unwrap(), jpeg());
+    assert!(part_files(&harness).is_empty());
+    assert!(!tray.archive().quarantine_dir().exists());
+}
+
+#[test]
+fn prune_removes_stale_part_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let stale = dir.path().join("wallpaper.jpg.part");
+    let fresh = dir.path().join("other.jpg.1234-0.part");
+    std::fs::write(&stale, b"partial").unwrap();
+    std::fs::write(&fresh, b"partial").unwrap();
+    std::fs::File::options()
+        .write(true)
+        .open(&stale)
+        .unwrap()
+        .set_modified(SystemTime::now() - Duration::from_secs(2 * 24 * 60 * 60))
+        .unwrap();
+
+    Archive::new(dir.path().to_path_buf()).prune().unwrap();
+
+    assert!(!stale.exists());
+    assert!(fresh.exists());
+}
+
+#[tokio::test]
+async fn resumes_truncated_download() {
+    let harness = Harness::start().await;
+    harness.server.set(|b| b.truncate = 1);
+    let service = harness.service();
+
+    service.update_wallpaper().await.unwrap();
+
+    let applied = harness.setter.applied();
+    assert_eq!(applied.len(), 1);
+    assert_eq!(std::fs::read(&applied[0]).unwrap(), jpeg());
+
+    let hits = harness.server.hits();
+    let images = hits
+        .iter()
+        .filter(|hit| hit.starts_with("/th"))
+        .collect::<Vec<_>>();
+    assert_eq!(images.len(), 2);
+    assert!(!images[0].contains("range="));
+    assert!(images[1].contains("range=bytes="));
+    assert!(part_files(&harness).is_empty());
+}
+
+fn part_files(harness: &Harness) -> Vec<std::path::PathBuf> {
+    std::fs::read_dir(harness.dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.to_string_lossy().ends_with(".part"))
+        .collect()
+}
+
+#[tokio::test]
+async fn backfill_skips_archived_images() {
+    let harness = Harness::start().await;
+    harness
+        .server
+        .set(|b| b.metadata = Metadata::Images((0..10).map(hp_image).collect()));
+    let service = harness.service();
+
+    service.update_wallpaper().await.unwrap();
+    let report = service
+        .backfill(std::slice::from_ref(service.source()))
+        .await
+        .unwrap();
+
+    assert_eq!(report.downloaded.len(), 9);
+    assert_eq!(report.skipped, 1);
+    assert_eq!(report.failed, 0);
+    assert_eq!(harness.server.count("/th"), 10);
+    assert_eq!(service.archive().entries().unwrap().len(), 10);
+    assert_eq!(harness.setter.applied().len(), 1);
+}
+
+#[tokio::test]
+async fn backfill_skips_images_older_than_keep_days() {
+    let harness = Harness::start().await;
+    harness
+        .server
+        .set(|b| b.metadata = Metadata::Images((0..10).map(hp_image).collect()));
+    let archive = Archive::new(harness.dir.path().to_path_buf()).with_retention(Retention {
+        keep_days: Some(3),
+        max_bytes: None,
+    });
+    let service = harness.service().with_archive(archive);
+
+    service.update_wallpaper().await.unwrap();
+    let report = service
+        .backfill(std::slice::from_ref(service.source()))
+        .await
+        .unwrap();
+
+    assert_eq!(report.downloaded.len(), 3);
+    assert!(report.downloaded.iter().all(|path| path.exists()));
+    assert_eq!(report.skipped, 7);
+    assert_eq!(service.archive().entries().unwrap().len(), 4);
+
+    let report = service
+        .backfill(std::slice::from_ref(service.source()))
+        .await
+        .unwrap();
+    assert!(report.downloaded.is_empty());
+    assert_eq!(report.skipped, 10);
+    assert_eq!(harness.server.count("/th"), 4);
+}
+
+#[tokio::test]
+async fn fetch_downloads_without_applying() {
+    let harness = Harness::start().await;
+    let service = harness.service();
+    let out = tempfile::tempdir().unwrap();
+
+    let path = service.fetch_wallpaper(out.path()).await.unwrap();
+
+    assert!(path.starts_with(out.path()));
+    assert_eq!(std::fs::read(&path).unwrap(), jpeg());
+    assert!(harness.setter.applied().is_empty());
+    assert!(service.archive().entries().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn notifies_only_when_wallpaper_changes() {
+    let harness = Harness::start().await;
+    let notified = Arc::new(Mutex::new(Vec::new()));
+    let service = harness.service().with_on_applied({
+        let notified = notified.clone();
+        Arc::new(move |image, path| {
+            notified
+                .lock()
+                .unwrap()
+                .push((image.id.clone(), path.to_path_buf()));
+        })
+    });
+
+    service.update_wallpaper().await.unwrap();
+    service.update_wallpaper().await.unwrap();
+
+    let notified = notified.lock().unwrap().clone();
+    assert_eq!(
+        notified,
+        vec![("h0".to_string(), harness.setter.applied()[0].clone())]
+    );
+}
+
+#[tokio::test]
+async fn manual_update_uses_reloaded_config() {
+    let harness = Harness::start().await;
+    let other = FakeBing::start().await;
+    let mut service = harness.service();
+    let (sender, mut config) = watch::channel(Arc::new(Config::default()));
+
+    assert!(!service.reconfigure_if_changed(&mut config).unwrap());
+
+    let mut reloaded = Config::default();
+    reloaded.bing.host = Some(other.url().to_string());
+    reloaded.bing.market = "en-US".to_string();
+    reloaded.image.resolution = "1920x1080".to_string();
+    reloaded.archive.dir = Some(harness.dir.path().join("reloaded"));
+    sender.send(Arc::new(reloaded)).unwrap();
+
+    assert!(service.reconfigure_if_changed(&mut config).unwrap());
+    service.update_wallpaper().await.unwrap();
+
+    assert_eq!(harness.server.hits(), Vec::<String>::new());
+    assert!(other.hits()[0].contains("mkt=en-US"));
+    let applied = harness.setter.applied();
+    assert_eq!(applied.len(), 1);
+    assert!(applied[0].starts_with(harness.dir.path().join("reloaded")));
+}
+
+#[tokio::test]
+async fn daemon_backfills_once_per_day() {
+    let harness = Harness::start().await;
+    let service = harness
+        .service()
+        .with_backfill(Vec::new())
+        .with_schedule(Schedule {
+            smart: false,
+            interval: Duration::from_millis(50),
+            ..Schedule::default()
+        });
+    let (_sender, config) = watch::channel(Arc::new(Config::default()));
+    let (_triggers, receiver) = mpsc::channel(8);
+
+    let daemon = tokio::spawn(service.run_daily_updating(config, receiver));
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    daemon.abort();
+
+    let hits = harness.server.hits();
+    let metadata = |n: &str| {
+        hits.iter()
+            .filter(|hit| hit.starts_with("/HPImageArchive.aspx") && hit.ends_with(n))
+            .count()
+    };
+    assert!(metadata("&n=1") >= 3, "{:?}", hits);
+    assert_eq!(metadata("&n=8"), 2, "{:?}", hits);
+}
+
+/// 未通过校验的图片移入隔离目录，不应用也不加入壁纸库
+async fn assert_rejected(body: Vec<u8>, content_type: &'static str) {
+    let harness = Harness::start().await;
+    harness.server.set(|b| {
+        b.image_body = Some(body);
+        b.image_type = content_type;
+    });
+    let service = harness.service();
+
+    let result = service.update_wallpaper().await;
+
+    assert!(
+        matches!(result, Err(WallpaperError::InvalidImage(_))),
+        "{:?}",
+        result
+    );
+    let quarantined = std::fs::read_dir(service.archive().quarantine_dir())
+        .unwrap()
+        .count();
+    assert_eq!(quarantined, 1);
+    assert!(harness.setter.applied().is_empty());
+    assert!(service.archive().entries().unwrap().is_empty());
+    assert!(service.state().get().await.image.is_none());
+}
+
+#[tokio::test]
+async fn rejects_truncated_image() {
+    let bytes = jpeg();
+    assert_rejected(bytes[..bytes.len() / 2].to_vec(), "image/jpeg").await;
+}
+
+#[tokio::test]
+async fn rejects_undecodable_image() {
+    let mut bytes = jpeg()[..64].to_vec();
+    bytes.extend(std::iter::repeat_n(0x55, 4096));
+    bytes.extend([0xFF, 0xD9]);
+    assert_rejected(bytes, "image/jpeg").await;
+}
+
+#[tokio::test]
+async fn rejects_too_small_image() {
+    let image = RgbImage::from_pixel(320, 180, image::Rgb([0, 128, 255]));
+    let mut bytes = Cursor::new(Vec::new());
+    image.write_to(&mut bytes, ImageFormat::Jpeg).unwrap();
+    assert_rejected(bytes.into_inner(), "image/jpeg").await;
+}
+
+#[tokio::test]
+async fn rejects_html_served_as_image() {
+    let html = b"<!DOCTYPE html><html><body>Access denied</body></html>".to_vec();
+    assert_rejected(html, "image/jpeg").await;
+}
@@ -0,0 +1,84 @@
+use bingwallpaper::InvalidImage;
+use bingwallpaper::WallpaperError;
+use reqwest::StatusCode;
+use std::io;
+use std::net::TcpListener;
+
+fn http_status(status: u16) -> WallpaperError {
+    WallpaperError::HttpStatus {
+        url: "http://example.com/HPImageArchive.aspx".to_string(),
+        status: StatusCode::from_u16(status).unwrap(),
+    }
+}
+
+#[tokio::test]
+async fn connection_failure_is_network() {
+    // 先占用端口再释放，确保连接被拒绝
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let url = format!("http://127.0.0.1:{}/", port);
+    let source = reqwest::get(&url).await.unwrap_err();
+    let e = WallpaperError::Network { url, source };
+
+    assert!(e.is_network());
+    assert_eq!(e.exit_code(), 3);
+}
+
+#[test]
+fn only_server_errors_and_throttling_are_network() {
+    for (status, network, code) in [
+        (500, true, 3),
+        (503, true, 3),
+        (429, true, 3),
+        (404, false, 8),
+        (403, false, 8),
+        (400, false, 8),
+    ] {
+        let e = http_status(status);
+        assert_eq!(e.is_network(), network, "{}", status);
+        assert_eq!(e.exit_code(), code, "{}", status);
+    }
+}
+
+#[test]
+fn maps_error_kinds_to_exit_codes() {
+    let cases = [
+        (
+            WallpaperError::Interrupted {
+                url: "http://example.com/a.jpg".to_string(),
+                reason: "停滞".to_string(),
+            },
+            3,
+        ),
+        (WallpaperError::Config("bing.n 无效".to_string()), 2),
+        (WallpaperError::EmptyFeed("bing".to_string()), 4),
+        (
+            WallpaperError::Parse {
+                what: "bing".to_string(),
+                source: "expected value".into(),
+            },
+            4,
+        ),
+        (
+            WallpaperError::InvalidImage(InvalidImage("太小".to_string())),
+            5,
+        ),
+        (WallpaperError::Unsupported("未知桌面".to_string()), 6),
+        (WallpaperError::Platform("gsettings".to_string()), 6),
+        (
+            WallpaperError::Io {
+                path: None,
+                source: io::Error::from(io::ErrorKind::PermissionDenied),
+            },
+            7,
+        ),
+    ];
+
+    for (e, code) in cases {
+        assert_eq!(e.exit_code(), code, "{}", e);
+        assert_eq!(e.is_network(), code == 3, "{}", e);
+    }
+}
@@ -0,0 +1,205 @@
+use bingwallpaper::Downloader;
+use bingwallpaper::ImageMeta;
+use bingwallpaper::ImageSource;
+use bingwallpaper::LocalSource;
+use bingwallpaper::SlideshowOrder;
+use bingwallpaper::WallpaperError;
+use bingwallpaper::WallpaperService;
+use std::collections::HashSet;
+use std::path::MAIN_SEPARATOR_STR;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::OnceLock;
+use std::time::Duration;
+use std::time::SystemTime;
+
+/// 只按扩展名识别图片，内容不需要是有效的图片
+fn fixtures(dir: &Path, files: &[&str]) {
+    for (i, file) in files.iter().enumerate() {
+        let path = dir.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, b"image").unwrap();
+        // 第一张最旧
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000 + i as u64 * 60);
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+}
+
+/// 本地来源不发送请求，共用一个以免反复创建 HTTP 客户端
+fn http() -> &'static Downloader {
+    static HTTP: OnceLock<Downloader> = OnceLock::new();
+    HTTP.get_or_init(|| Downloader::new(reqwest::Client::new()))
+}
+
+/// 取下一张并标记为已应用
+async fn next(source: &LocalSource) -> ImageMeta {
+    let image = source.latest(http()).await.unwrap();
+    source.applied(&image).unwrap();
+    image
+}
+
+async fn titles(source: &LocalSource, n: usize) -> Vec<String> {
+    let mut titles = Vec::new();
+    for _ in 0..n {
+        titles.push(next(source).await.title);
+    }
+    titles
+}
+
+#[tokio::test]
+async fn sequential_cycles_images_in_top_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    fixtures(
+        dir.path(),
+        &["b.jpg", "a.png", "c.JPEG", "notes.txt", "sub/d.jpg"],
+    );
+    let source = LocalSource::new(dir.path().to_path_buf());
+
+    assert_eq!(titles(&source, 4).await, ["a", "b", "c", "a"]);
+}
+
+#[tokio::test]
+async fn filters_recursive_images() {
+    let dir = tempfile::tempdir().unwrap();
+    fixtures(
+        dir.path(),
+        &[
+            "a.jpg",
+            "b.png",
+            "sub/c.JPG",
+            "sub/skip/d.jpg",
+            "sub/deeper/e.jpg",
+        ],
+    );
+    let source = LocalSource::new(dir.path().to_path_buf())
+        .with_recursive(true)
+        .with_filters(&["*.jpg".to_string()], &["sub/skip/**".to_string()])
+        .unwrap();
+
+    let expected =
+        ["a", "sub/c", "sub/deeper/e"].map(|title| title.replace('/', MAIN_SEPARATOR_STR));
+    assert_eq!(titles(&source, 3).await, expected);
+}
+
+#[tokio::test]
+async fn shuffle_shows_every_image_before_repeating() {
+    let dir = tempfile::tempdir().unwrap();
+    fixtures(dir.path(), &["a.jpg", "b.jpg", "c.jpg", "d.jpg"]);
+    let source = LocalSource::new(dir.path().to_path_buf()).with_order(SlideshowOrder::Shuffle);
+
+    for _ in 0..5 {
+        let round = titles(&source, 4).await;
+        assert_eq!(round.iter().collect::<HashSet<_>>().len(), 4, "{:?}", round);
+        let following = source.latest(http()).await.unwrap();
+        assert_ne!(following.title, round[3]);
+    }
+}
+
+#[tokio::test]
+async fn recent_never_repeats_current_image() {
+    let dir = tempfile::tempdir().unwrap();
+    fixtures(dir.path(), &["a.jpg", "b.jpg", "c.jpg"]);
+    let source = LocalSource::new(dir.path().to_path_buf()).with_order(SlideshowOrder::Recent);
+
+    let mut last = next(&source).await.title;
+    let mut seen = HashSet::new();
+    for _ in 0..50 {
+        let title = next(&source).await.title;
+        assert_ne!(title, last);
+        seen.insert(title.clone());
+        last = title;
+    }
+    assert_eq!(seen.len(), 3);
+}
+
+#[tokio::test]
+async fn single_image_is_repeated() {
+    let dir = tempfile::tempdir().unwrap();
+    fixtures(dir.path(), &["only.webp"]);
+
+    for order in [
+        SlideshowOrder::Sequential,
+        SlideshowOrder::Shuffle,
+        SlideshowOrder::Recent,
+    ] {
+        let source = LocalSource::new(dir.path().to_path_buf()).with_order(order);
+        assert_eq!(titles(&source, 2).await, ["only", "only"]);
+    }
+}
+
+#[tokio::test]
+async fn history_is_newest_first() {
+    let dir = tempfile::tempdir().unwrap();
+    fixtures(dir.path(), &["a.jpg", "b.jpg", "c.jpg"]);
+    let source = LocalSource::new(dir.path().to_path_buf());
+
+    let history = source.history(http(), 2).await.unwrap();
+
+    let titles = history
+        .iter()
+        .map(|image| image.title.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(titles, ["c", "b"]);
+}
+
+#[tokio::test]
+async fn resumes_progress_from_state_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = tempfile::tempdir().unwrap();
+    let state_path = state.path().join("slideshow.json");
+    fixtures(dir.path(), &["a.jpg", "b.jpg", "c.jpg"]);
+
+    let source = LocalSource::new(dir.path().to_path_buf()).with_state_path(state_path.clone());
+    assert_eq!(titles(&source, 2).await, ["a", "b"]);
+
+    let source = LocalSource::new(dir.path().to_path_buf()).with_state_path(state_path);
+    assert_eq!(titles(&source, 2).await, ["c", "a"]);
+}
+
+#[tokio::test]
+async fn reports_empty_and_missing_dirs() {
+    let dir = tempfile::tempdir().unwrap();
+    fixtures(dir.path(), &["notes.txt"]);
+
+    let empty = LocalSource::new(dir.path().to_path_buf())
+        .latest(http())
+        .await;
+    assert!(
+        matches!(empty, Err(WallpaperError::EmptyFeed(_))),
+        "{:?}",
+        empty
+    );
+
+    let missing = LocalSource::new(dir.path().join("missing"))
+        .latest(http())
+        .await;
+    assert!(
+        matches!(missing, Err(WallpaperError::Io { .. })),
+        "{:?}",
+        missing
+    );
+}
+
+#[tokio::test]
+async fn fetch_copies_image_to_out_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    fixtures(dir.path(), &["a.jpg"]);
+    let service = WallpaperService::with_downloader(http().clone())
+        .with_source(Arc::new(LocalSource::new(dir.path().to_path_buf())));
+
+    let out = tempfile::tempdir().unwrap();
+    let path = service.fetch_wallpaper(out.path()).await.unwrap();
+    assert_eq!(path.file_name().unwrap(), "a.jpg");
+    assert_eq!(path.parent().unwrap(), out.path().canonicalize().unwrap());
+    assert_eq!(std::fs::read(&path).unwrap(), b"image");
+
+    // 输出到图片所在的目录时不复制
+    let path = service.fetch_wallpaper(dir.path()).await.unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"image");
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
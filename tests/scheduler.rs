@@ -0,0 +1,190 @@
+use bingwallpaper::Fire;
+use bingwallpaper::HpImage;
+use bingwallpaper::Market;
+use bingwallpaper::Schedule;
+use bingwallpaper::Scheduler;
+use bingwallpaper::Trigger;
+use std::time::Duration;
+use time::macros::datetime;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tokio::time::timeout;
+
+const INTERVAL: Duration = Duration::from_secs(3600);
+const DEBOUNCE: Duration = Duration::from_secs(10);
+
+fn scheduler(schedule: Schedule) -> (Scheduler, mpsc::Sender<Trigger>) {
+    let (sender, receiver) = mpsc::channel(8);
+    (Scheduler::new(schedule, receiver), sender)
+}
+
+fn schedule() -> Schedule {
+    Schedule {
+        interval: INTERVAL,
+        debounce: DEBOUNCE,
+        ..Schedule::default()
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn fires_immediately_then_every_interval() {
+    let (mut scheduler, _sender) = scheduler(schedule());
+    let start = Instant::now();
+
+    assert_eq!(scheduler.next().await, Fire::Scheduled);
+    assert_eq!(start.elapsed(), Duration::ZERO);
+    assert_eq!(scheduler.next().await, Fire::Scheduled);
+    assert_eq!(start.elapsed(), INTERVAL);
+}
+
+#[tokio::test(start_paused = true)]
+async fn debounces_bursts_of_events() {
+    let (mut scheduler, sender) = scheduler(schedule());
+    scheduler.next().await;
+    let start = Instant::now();
+
+    sender.send(Trigger::Resume).await.unwrap();
+    sender.send(Trigger::NetworkOnline).await.unwrap();
+    sender.send(Trigger::NetworkOnline).await.unwrap();
+
+    assert_eq!(
+        scheduler.next().await,
+        Fire::Trigger(Trigger::NetworkOnline)
+    );
+    assert_eq!(start.elapsed(), DEBOUNCE);
+    assert!(
+        timeout(DEBOUNCE * 2, scheduler.next()).await.is_err(),
+        "一串事件只应触发一次更新"
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn ignores_disabled_triggers() {
+    let (mut scheduler, sender) = scheduler(Schedule {
+        on_resume: false,
+        ..schedule()
+    });
+    scheduler.next().await;
+
+    sender.send(Trigger::Resume).await.unwrap();
+    assert!(timeout(DEBOUNCE * 2, scheduler.next()).await.is_err());
+
+    sender.send(Trigger::NetworkOnline).await.unwrap();
+    assert_eq!(
+        scheduler.next().await,
+        Fire::Trigger(Trigger::NetworkOnline)
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn trigger_restarts_interval() {
+    let (mut scheduler, sender) = scheduler(schedule());
+    scheduler.next().await;
+
+    tokio::time::advance(INTERVAL / 2).await;
+    sender.send(Trigger::Resume).await.unwrap();
+    assert_eq!(scheduler.next().await, Fire::Trigger(Trigger::Resume));
+
+    let fired = Instant::now();
+    assert_eq!(scheduler.next().await, Fire::Scheduled);
+    assert_eq!(fired.elapsed(), INTERVAL);
+}
+
+#[tokio::test(start_paused = true)]
+async fn interval_change_takes_effect_from_now() {
+    let (mut scheduler, _sender) = scheduler(schedule());
+    scheduler.next().await;
+
+    let changed = Instant::now();
+    scheduler.set_schedule(Schedule {
+        interval: INTERVAL * 2,
+        ..schedule()
+    });
+    assert_eq!(scheduler.next().await, Fire::Scheduled);
+    assert_eq!(changed.elapsed(), INTERVAL * 2);
+}
+
+#[tokio::test(start_paused = true)]
+async fn keeps_ticking_after_event_source_closes() {
+    let (mut scheduler, sender) = scheduler(schedule());
+    scheduler.next().await;
+    drop(sender);
+
+    assert_eq!(scheduler.next().await, Fire::Scheduled);
+}
+
+#[tokio::test(start_paused = true)]
+async fn wakes_at_requested_time() {
+    let (mut scheduler, _sender) = scheduler(schedule());
+    scheduler.next().await;
+
+    let woken = Instant::now();
+    scheduler.wake_after(Duration::from_secs(90));
+    assert_eq!(scheduler.next().await, Fire::Scheduled);
+    assert_eq!(woken.elapsed(), Duration::from_secs(90));
+}
+
+#[test]
+fn rollover_follows_full_start_date() {
+    let image = HpImage {
+        fullstartdate: "202610171600".to_string(),
+        enddate: "20261018".to_string(),
+        ..HpImage::default()
+    };
+    let market = Market::new("zh-CN").unwrap();
+    assert_eq!(
+        image.next_rollover(market),
+        Some(datetime!(2026-10-18 16:00 UTC))
+    );
+
+    let image = HpImage {
+        enddate: "20261018".to_string(),
+        ..HpImage::default()
+    };
+    assert_eq!(
+        image.next_rollover(market),
+        Some(datetime!(2026-10-18 00:00 +8))
+    );
+    assert_eq!(HpImage::default().next_rollover(market), None);
+}
+
+#[test]
+fn sleeps_until_just_after_rollover() {
+    let schedule = schedule();
+    let rollover = datetime!(2026-10-18 16:00 UTC);
+
+    assert_eq!(
+        schedule.next_wake(Some(rollover), datetime!(2026-10-18 10:00 UTC)),
+        Duration::from_secs(6 * 3600) + schedule.rollover_delay
+    );
+}
+
+#[test]
+fn polls_briefly_when_image_is_late() {
+    let schedule = schedule();
+    let rollover = datetime!(2026-10-18 16:00 UTC);
+
+    assert_eq!(
+        schedule.next_wake(Some(rollover), datetime!(2026-10-18 16:10 UTC)),
+        schedule.poll_interval
+    );
+    assert_eq!(
+        schedule.next_wake(Some(rollover), datetime!(2026-10-18 18:00 UTC)),
+        INTERVAL
+    );
+}
+
+#[test]
+fn falls_back_to_interval() {
+    let now = datetime!(2026-10-18 10:00 UTC);
+    assert_eq!(schedule().next_wake(None, now), INTERVAL);
+
+    let fixed = Schedule {
+        smart: false,
+        ..schedule()
+    };
+    assert_eq!(
+        fixed.next_wake(Some(datetime!(2026-10-18 16:00 UTC)), now),
+        INTERVAL
+    );
+}
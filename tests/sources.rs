@@ -0,0 +1,393 @@
+use axum::Router;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::http::StatusCode;
+use axum::http::Uri;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use bingwallpaper::ApodSource;
+use bingwallpaper::Downloader;
+use bingwallpaper::ImageSource;
+use bingwallpaper::RetryPolicy;
+use bingwallpaper::Timeouts;
+use bingwallpaper::UnsplashSource;
+use bingwallpaper::WallpaperError;
+use bingwallpaper::WikimediaSource;
+use image::ImageFormat;
+use image::RgbImage;
+use serde_json::json;
+use std::io::Cursor;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+/// 按路径前缀返回固定内容
+#[derive(Clone)]
+struct Fixture {
+    prefix: &'static str,
+    status: StatusCode,
+    content_type: &'static str,
+    body: Vec<u8>,
+    delay: Duration,
+}
+
+impl Fixture {
+    fn json(prefix: &'static str, body: impl ToString) -> Self {
+        Self {
+            prefix,
+            status: StatusCode::OK,
+            content_type: "application/json",
+            body: body.to_string().into_bytes(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    fn status(prefix: &'static str, status: StatusCode) -> Self {
+        Self {
+            status,
+            ..Self::json(prefix, "")
+        }
+    }
+
+    fn jpeg(prefix: &'static str) -> Self {
+        let image = RgbImage::from_pixel(800, 450, image::Rgb([32, 64, 128]));
+        let mut bytes = Cursor::new(Vec::new());
+        image.write_to(&mut bytes, ImageFormat::Jpeg).unwrap();
+        Self {
+            content_type: "image/jpeg",
+            body: bytes.into_inner(),
+            ..Self::json(prefix, "")
+        }
+    }
+
+    fn delayed(self, delay: Duration) -> Self {
+        Self { delay, ..self }
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    fixtures: Mutex<Vec<Fixture>>,
+    /// 请求地址与认证相关的请求头
+    hits: Mutex<Vec<(String, Option<String>)>>,
+}
+
+struct FixtureServer {
+    url: String,
+    shared: Arc<Shared>,
+}
+
+impl FixtureServer {
+    async fn start(fixtures: Vec<Fixture>) -> Self {
+        let shared = Arc::new(Shared {
+            fixtures: Mutex::new(fixtures),
+            ..Shared::default()
+        });
+        let app = Router::new().fallback(serve).with_state(shared.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        Self { url, shared }
+    }
+
+    /// 用于内容中需要包含服务器地址的情况
+    fn add(&self, fixture: Fixture) {
+        self.shared.fixtures.lock().unwrap().push(fixture);
+    }
+
+    fn hits(&self) -> Vec<(String, Option<String>)> {
+        self.shared.hits.lock().unwrap().clone()
+    }
+}
+
+async fn serve(State(shared): State<Arc<Shared>>, uri: Uri, headers: HeaderMap) -> Response {
+    let auth = [header::AUTHORIZATION.as_str(), "x-api-key"]
+        .into_iter()
+        .find_map(|name| headers.get(name))
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    shared.hits.lock().unwrap().push((uri.to_string(), auth));
+
+    let fixture = shared
+        .fixtures
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|fixture| uri.path().starts_with(fixture.prefix))
+        .cloned();
+    let Some(fixture) = fixture else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    tokio::time::sleep(fixture.delay).await;
+
+    (
+        fixture.status,
+        [(header::CONTENT_TYPE, fixture.content_type)],
+        fixture.body,
+    )
+        .into_response()
+}
+
+fn downloader() -> Downloader {
+    Downloader::new(reqwest::Client::new())
+        .with_retry(RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        })
+        .with_timeouts(Timeouts {
+            metadata: Duration::from_millis(500),
+            ..Timeouts::default()
+        })
+}
+
+#[tokio::test]
+async fn apod_parses_latest_image() {
+    let server = FixtureServer::start(vec![Fixture::json(
+        "/planetary/apod",
+        json!([
+            {
+                "date": "2026-10-15",
+                "title": "Older",
+                "media_type": "image",
+                "url": "https://apod.nasa.gov/apod/image/older.jpg"
+            },
+            {
+                "date": "2026-10-16",
+                "title": "Galaxy",
+                "copyright": "\nJane\n  Doe\n",
+                "media_type": "image",
+                "url": "https://apod.nasa.gov/apod/image/galaxy_1024.jpg",
+                "hdurl": "https://apod.nasa.gov/apod/image/galaxy.jpg"
+            },
+            {
+                "date": "2026-10-17",
+                "title": "A video",
+                "media_type": "video",
+                "url": "https://www.youtube.com/embed/xyz"
+            }
+        ]),
+    )])
+    .await;
+    let source = ApodSource::new(Some(&server.url), Some("secret")).unwrap();
+
+    let image = source.latest(&downloader()).await.unwrap();
+
+    assert_eq!(image.source, "apod");
+    assert_eq!(image.id, "2026-10-16");
+    assert_eq!(image.date, "20261016");
+    assert_eq!(image.title, "Galaxy");
+    assert_eq!(image.copyright, "Jane Doe");
+    assert_eq!(image.link, "https://apod.nasa.gov/apod/ap261016.html");
+    assert_eq!(
+        image.urls,
+        vec![
+            "https://apod.nasa.gov/apod/image/galaxy.jpg",
+            "https://apod.nasa.gov/apod/image/galaxy_1024.jpg",
+        ]
+    );
+    let (url, auth) = &server.hits()[0];
+    assert!(url.contains("start_date=") && url.contains("end_date="));
+    assert!(!url.contains("secret"));
+    assert_eq!(auth.as_deref(), Some("secret"));
+}
+
+#[tokio::test]
+async fn apod_maps_errors() {
+    let videos = json!([{ "date": "2026-10-17", "media_type": "video", "url": "x" }]);
+    let cases = [
+        (Fixture::json("/planetary/apod", videos), "EmptyFeed"),
+        (Fixture::json("/planetary/apod", "<html>"), "Parse"),
+        (
+            Fixture::status("/planetary/apod", StatusCode::FORBIDDEN),
+            "HttpStatus",
+        ),
+    ];
+
+    for (fixture, expected) in cases {
+        let server = FixtureServer::start(vec![fixture]).await;
+        let source = ApodSource::new(Some(&server.url), None).unwrap();
+        let result = source.latest(&downloader()).await;
+        assert_error(result, expected);
+    }
+}
+
+#[tokio::test]
+async fn wikimedia_parses_featured_image() {
+    let server = FixtureServer::start(vec![Fixture::json(
+        "/feed/v1/wikipedia/de/featured/",
+        json!({
+            "image": {
+                "title": "File:Alpine lake.jpg",
+                "thumbnail": {
+                    "source": "https://upload.wikimedia.org/thumb/a/ab/Alpine_lake.jpg/640px-Alpine_lake.jpg",
+                    "width": 640
+                },
+                "image": {
+                    "source": "https://upload.wikimedia.org/a/ab/Alpine_lake.jpg",
+                    "width": 4000
+                },
+                "file_page": "https://commons.wikimedia.org/wiki/File:Alpine_lake.jpg",
+                "artist": { "text": "Jane Doe" },
+                "license": { "type": "CC BY-SA 4.0" },
+                "description": { "text": "" }
+            }
+        }),
+    )])
+    .await;
+    let source = WikimediaSource::new(
+        Some(&server.url),
+        Some("de"),
+        Some("1920x1080".parse().unwrap()),
+    )
+    .unwrap();
+
+    let image = source.latest(&downloader()).await.unwrap();
+
+    assert_eq!(image.source, "wikimedia");
+    assert_eq!(image.id, "File:Alpine lake.jpg");
+    assert_eq!(image.title, "Alpine lake");
+    assert_eq!(image.copyright, "Jane Doe / CC BY-SA 4.0");
+    assert_eq!(
+        image.link,
+        "https://commons.wikimedia.org/wiki/File:Alpine_lake.jpg"
+    );
+    assert_eq!(
+        image.urls,
+        vec![
+            "https://upload.wikimedia.org/thumb/a/ab/Alpine_lake.jpg/1920px-Alpine_lake.jpg",
+            "https://upload.wikimedia.org/a/ab/Alpine_lake.jpg",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn wikimedia_maps_errors() {
+    let prefix = "/feed/v1/wikipedia/en/featured/";
+    let cases = [
+        (Fixture::json(prefix, json!({ "tfa": {} })), "EmptyFeed"),
+        (Fixture::json(prefix, "not json"), "Parse"),
+        (Fixture::status(prefix, StatusCode::NOT_FOUND), "HttpStatus"),
+    ];
+
+    for (fixture, expected) in cases {
+        let server = FixtureServer::start(vec![fixture]).await;
+        let source = WikimediaSource::new(Some(&server.url), None, None).unwrap();
+        let result = source.latest(&downloader()).await;
+        assert_error(result, expected);
+    }
+}
+
+fn unsplash_photo(server: &FixtureServer) -> serde_json::Value {
+    json!([{
+        "id": "abc123",
+        "description": null,
+        "alt_description": "a lake in the mountains",
+        "urls": {
+            "raw": format!("{}/photo-abc123?ixid=xyz", server.url),
+            "full": format!("{}/photo-abc123-full", server.url)
+        },
+        "links": { "html": "https://unsplash.com/photos/abc123" },
+        "user": { "name": "Jane Doe" }
+    }])
+}
+
+#[tokio::test]
+async fn unsplash_parses_random_photo() {
+    let server = FixtureServer::start(Vec::new()).await;
+    server.add(Fixture::json("/photos/random", unsplash_photo(&server)));
+    let source = UnsplashSource::new(
+        Some(&server.url),
+        "key",
+        Some("nature"),
+        Some("1920x1080".parse().unwrap()),
+    )
+    .unwrap();
+
+    let image = source.latest(&downloader()).await.unwrap();
+
+    assert_eq!(image.source, "unsplash");
+    assert_eq!(image.id, "abc123");
+    assert_eq!(image.title, "a lake in the mountains");
+    assert_eq!(image.copyright, "Photo by Jane Doe on Unsplash");
+    assert_eq!(image.link, "https://unsplash.com/photos/abc123");
+    assert_eq!(
+        image.urls,
+        vec![
+            format!(
+                "{}/photo-abc123?ixid=xyz&w=1920&h=1080&fit=crop&fm=jpg&q=85",
+                server.url
+            ),
+            format!("{}/photo-abc123-full", server.url),
+        ]
+    );
+    let (url, auth) = &server.hits()[0];
+    assert!(url.contains("count=1"), "{}", url);
+    assert!(url.contains("orientation=landscape"), "{}", url);
+    assert!(url.contains("query=nature"), "{}", url);
+    assert_eq!(auth.as_deref(), Some("Client-ID key"));
+}
+
+#[tokio::test]
+async fn unsplash_maps_errors() {
+    let cases = [
+        (Fixture::json("/photos/random", json!([])), "EmptyFeed"),
+        (
+            Fixture::json("/photos/random", json!({ "errors": [] })),
+            "Parse",
+        ),
+        (
+            Fixture::status("/photos/random", StatusCode::UNAUTHORIZED),
+            "HttpStatus",
+        ),
+    ];
+
+    for (fixture, expected) in cases {
+        let server = FixtureServer::start(vec![fixture]).await;
+        let source = UnsplashSource::new(Some(&server.url), "key", None, None).unwrap();
+        let result = source.latest(&downloader()).await;
+        assert_error(result, expected);
+    }
+}
+
+#[tokio::test]
+async fn unsplash_download_does_not_wait_for_hung_tracking() {
+    let server = FixtureServer::start(vec![
+        Fixture::json("/photos/abc123/download", json!({})).delayed(Duration::from_secs(30)),
+        Fixture::jpeg("/photo-abc123"),
+    ])
+    .await;
+    server.add(Fixture::json("/photos/random", unsplash_photo(&server)));
+    let source = UnsplashSource::new(Some(&server.url), "key", None, None).unwrap();
+    let http = downloader();
+    let dir = tempfile::tempdir().unwrap();
+
+    let image = source.latest(&http).await.unwrap();
+    let path = tokio::time::timeout(
+        Duration::from_secs(5),
+        source.download(&http, &image, dir.path()),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    assert!(path.is_file());
+    assert!(
+        server
+            .hits()
+            .iter()
+            .any(|(url, _)| url == "/photos/abc123/download")
+    );
+}
+
+fn assert_error<T: std::fmt::Debug>(result: Result<T, WallpaperError>, expected: &str) {
+    let matched = match &result {
+        Err(WallpaperError::EmptyFeed(_)) => expected == "EmptyFeed",
+        Err(WallpaperError::Parse { .. }) => expected == "Parse",
+        Err(WallpaperError::HttpStatus { .. }) => expected == "HttpStatus",
+        _ => false,
+    };
+    assert!(matched, "expected {}, got {:?}", expected, result);
+}